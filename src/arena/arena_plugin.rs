@@ -0,0 +1,218 @@
+use bevy::{
+    app::Plugin,
+    asset::{Asset, Assets, Handle},
+    math::{Rect, Vec2, Vec3},
+    prelude::Resource,
+    reflect::TypePath,
+};
+use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_common_assets::ron::RonAssetPlugin;
+use serde::{Deserialize, Serialize};
+
+use crate::{GLASS_HEIGHT, GLASS_RADIUS, GLASS_WIDTH, WATER_LEVEL};
+
+/// Static wall of an arena, every wall is drawn and collides like the glass.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ArenaWall {
+    Rectangle {
+        center: Vec2,
+        size: Vec2,
+    },
+    /// Segments of `thickness` joined by round corners, for rounded or tapered containers.
+    Polyline {
+        points: Vec<Vec2>,
+        thickness: f32,
+    },
+}
+
+impl ArenaWall {
+    // lowest and highest point of the wall straight above or below `x`, once per segment crossing it
+    fn spans_at(&self, x: f32) -> Vec<(f32, f32)> {
+        match self {
+            ArenaWall::Rectangle { center, size } => {
+                if (x - center.x).abs() > size.x / 2. {
+                    return Vec::new();
+                }
+                vec![(center.y - size.y / 2., center.y + size.y / 2.)]
+            }
+            ArenaWall::Polyline { points, thickness } => points
+                .windows(2)
+                .filter_map(|segment| {
+                    let (a, b) = (segment[0], segment[1]);
+                    let dx = b.x - a.x;
+                    if dx == 0. || x < a.x.min(b.x) || x > a.x.max(b.x) {
+                        return None;
+                    }
+                    let y = a.y + (b.y - a.y) * (x - a.x) / dx;
+                    // half the thickness measured straight up across the slope
+                    let half = thickness / 2. * a.distance(b) / dx.abs();
+                    Some((y - half, y + half))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Drawn water line at rest, and the horizontal extent and floor of the water.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArenaWater {
+    pub level: f32,
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArenaBackground {
+    pub image: String,
+    pub position: Vec3,
+    pub scale: Vec2,
+}
+
+/// Container a match is played in, loaded from `assets/arenas/*.arena.ron`.
+/// The default is the original glass, used when no file is loaded.
+#[derive(Asset, Resource, TypePath, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Arena {
+    pub name: String,
+    pub walls: Vec<ArenaWall>,
+    pub water: ArenaWater,
+    /// Kept in view by the camera, tablets falling under it are eliminated.
+    pub bounds: Rect,
+    /// Spawn points of the tablets for each player count, from one player to four.
+    pub spawn_points: Vec<Vec<Vec2>>,
+    pub background: ArenaBackground,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        let bottom = -GLASS_HEIGHT / 2.;
+        Arena {
+            name: "Glass".to_string(),
+            walls: vec![
+                ArenaWall::Rectangle {
+                    center: Vec2::new(0., bottom),
+                    size: Vec2::new(GLASS_RADIUS * 2., GLASS_WIDTH),
+                },
+                ArenaWall::Rectangle {
+                    center: Vec2::new(-GLASS_RADIUS, 0.),
+                    size: Vec2::new(GLASS_WIDTH, GLASS_HEIGHT),
+                },
+                ArenaWall::Rectangle {
+                    center: Vec2::new(GLASS_RADIUS, 0.),
+                    size: Vec2::new(GLASS_WIDTH, GLASS_HEIGHT),
+                },
+            ],
+            water: ArenaWater {
+                level: bottom + WATER_LEVEL,
+                left: -GLASS_RADIUS,
+                right: GLASS_RADIUS,
+                bottom,
+            },
+            bounds: Rect::new(-GLASS_RADIUS, bottom, GLASS_RADIUS, -bottom),
+            spawn_points: vec![
+                vec![Vec2::ZERO],
+                vec![
+                    Vec2::new(-GLASS_RADIUS / 4.0, 0.),
+                    Vec2::new(GLASS_RADIUS / 4.0, 0.),
+                ],
+                vec![
+                    Vec2::new(-GLASS_RADIUS * 3. / 5.0, 0.),
+                    Vec2::ZERO,
+                    Vec2::new(GLASS_RADIUS * 3. / 5.0, 0.),
+                ],
+                vec![
+                    Vec2::new(-GLASS_RADIUS * 4. / 6.0, 0.),
+                    Vec2::new(-GLASS_RADIUS / 6.0, 0.),
+                    Vec2::new(GLASS_RADIUS / 6.0, 0.),
+                    Vec2::new(GLASS_RADIUS * 4. / 6.0, 0.),
+                ],
+            ],
+            background: ArenaBackground {
+                image: "audio/Kitchen.png".to_string(),
+                position: Vec3::new(0., 1000., -2.),
+                scale: Vec2::new(2., 2.),
+            },
+        }
+    }
+}
+
+impl Arena {
+    /// Height the water rests on at `x`: the top of the walls reaching under the water line there,
+    /// `water.bottom` where there are none.
+    pub fn floor_at(&self, x: f32) -> f32 {
+        self.walls
+            .iter()
+            .flat_map(|wall| wall.spans_at(x))
+            .filter(|(low, _)| *low <= self.water.level)
+            .map(|(_, high)| high)
+            .fold(self.water.bottom, f32::max)
+    }
+
+    /// Spawn point of `player` in a round of `player_number` tablets.
+    /// Missing points are spread evenly across the middle of the water.
+    pub fn spawn_point(&self, player_number: usize, player: usize) -> Vec2 {
+        self.spawn_points
+            .get(player_number.saturating_sub(1))
+            .and_then(|points| points.get(player))
+            .copied()
+            .unwrap_or_else(|| {
+                let step = (self.water.right - self.water.left) / (player_number + 1) as f32;
+                Vec2::new(
+                    self.water.left + step * (player + 1) as f32,
+                    (self.water.level + self.water.bottom) / 2.,
+                )
+            })
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct ArenaAssets {
+    #[asset(
+        paths(
+            "arenas/glass.arena.ron",
+            "arenas/mug.arena.ron",
+            "arenas/bowl.arena.ron"
+        ),
+        collection(typed)
+    )]
+    pub arenas: Vec<Handle<Arena>>,
+}
+
+/// Arena picked in the player menu, as an index in `ArenaAssets`.
+#[derive(Resource, Default)]
+pub struct ArenaChoice {
+    pub index: usize,
+}
+
+impl ArenaChoice {
+    pub fn next(&self, arena_number: usize) -> ArenaChoice {
+        ArenaChoice {
+            index: (self.index + 1) % arena_number.max(1),
+        }
+    }
+
+    /// The chosen arena, the default glass if the files are not loaded.
+    pub fn arena(&self, arena_assets: &ArenaAssets, arenas: &Assets<Arena>) -> Arena {
+        arena_assets
+            .arenas
+            .get(self.index)
+            .and_then(|handle| arenas.get(handle))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn label(&self, arena_assets: &ArenaAssets, arenas: &Assets<Arena>) -> String {
+        format!("Arena: {}", self.arena(arena_assets, arenas).name)
+    }
+}
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_plugins(RonAssetPlugin::<Arena>::new(&["arena.ron"]));
+        app.init_resource::<Arena>();
+        app.init_resource::<ArenaChoice>();
+    }
+}
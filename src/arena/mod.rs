@@ -0,0 +1 @@
+pub mod arena_plugin;
@@ -0,0 +1,6 @@
+use bevy_template::run_server;
+
+fn main() {
+    // [address to listen on] [number of players] [best of]
+    run_server();
+}
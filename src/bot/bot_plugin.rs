@@ -0,0 +1,231 @@
+use std::f32::consts::PI;
+
+use avian2d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::{
+    app::{FixedPreUpdate, Plugin},
+    math::{Vec2, Vec3, Vec3Swizzles},
+    prelude::{
+        in_state, Component, Entity, IntoSystemConfigs, Query, Res, Resource, Transform, With,
+    },
+    utils::default,
+};
+
+use crate::{
+    rollback::rollback_plugin::RollbackApp, tablet::tablet_plugin::Tablet,
+    water::water_plugin::Water, Health, PauseState, Player, PlayerIntent, SimulationSet, Team,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "Easy",
+            BotDifficulty::Normal => "Normal",
+            BotDifficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn next(&self) -> BotDifficulty {
+        match self {
+            BotDifficulty::Easy => BotDifficulty::Normal,
+            BotDifficulty::Normal => BotDifficulty::Hard,
+            BotDifficulty::Hard => BotDifficulty::Easy,
+        }
+    }
+
+    // fixed ticks between two decisions
+    fn reaction_ticks(&self) -> u32 {
+        match self {
+            BotDifficulty::Easy => 24,
+            BotDifficulty::Normal => 10,
+            BotDifficulty::Hard => 3,
+        }
+    }
+
+    // angle (radians) under which the bot considers itself aligned with its goal
+    fn aim_tolerance(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.6,
+            BotDifficulty::Normal => 0.35,
+            BotDifficulty::Hard => 0.2,
+        }
+    }
+
+    fn thrust(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.5,
+            BotDifficulty::Normal => 0.8,
+            BotDifficulty::Hard => 1.,
+        }
+    }
+
+    // fraction of the initial health under which the bot stops chasing and surfaces
+    fn surface_health(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.,
+            BotDifficulty::Normal => 0.25,
+            BotDifficulty::Hard => 0.4,
+        }
+    }
+}
+
+/// Marks a tablet driven by the computer instead of a keyboard or gamepad.
+#[derive(Component, Clone)]
+pub struct Bot {
+    pub difficulty: BotDifficulty,
+    cooldown: u32,
+}
+
+impl Bot {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        Bot {
+            difficulty,
+            cooldown: 0,
+        }
+    }
+}
+
+/// Difficulty of the bots, chosen in the player menu.
+#[derive(Resource, Default)]
+pub struct BotSettings {
+    pub difficulty: BotDifficulty,
+}
+
+/// Whether each `Player` index of the current match is driven by a bot.
+#[derive(Resource, Default)]
+pub struct BotPlayers(pub Vec<bool>);
+
+impl BotPlayers {
+    pub fn is_bot(&self, player: usize) -> bool {
+        self.0.get(player).copied().unwrap_or(false)
+    }
+}
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<BotSettings>();
+        app.init_resource::<BotPlayers>();
+        app.rollback_component::<Bot>();
+        app.add_systems(
+            FixedPreUpdate,
+            drive_bots
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+// distance to the glass walls under which the bot steers back to the center
+const WALL_MARGIN: f32 = 150.;
+// how far ahead (in seconds) the bot aims at a moving opponent
+const TARGET_LEAD: f32 = 0.3;
+// how much the current spin is taken into account when aiming
+const SPIN_ANTICIPATION: f32 = 0.3;
+
+fn drive_bots(
+    water: Res<Water>,
+    mut bots: Query<(
+        Entity,
+        &mut Bot,
+        &mut PlayerIntent,
+        &Transform,
+        &AngularVelocity,
+        &Health,
+        &Tablet,
+        &Team,
+    )>,
+    players: Query<(Entity, &Transform, &LinearVelocity, &Health, &Team), With<Player>>,
+) {
+    for (entity, mut bot, mut intent, transform, angular_velocity, health, tablet, team) in
+        &mut bots
+    {
+        if bot.cooldown > 0 {
+            bot.cooldown -= 1;
+            continue;
+        }
+        bot.cooldown = bot.difficulty.reaction_ticks();
+
+        let position = transform.translation.xy();
+        if !water.contains(&transform.translation) || health.0 <= 0. {
+            *intent = PlayerIntent::default();
+            continue;
+        }
+
+        // nearest opponent still in the game, teammates are left alone
+        let target = players
+            .iter()
+            .filter(|(other, _, _, other_health, other_team)| {
+                *other != entity && other_health.0 > 0. && *other_team != team
+            })
+            .map(|(_, other_transform, other_velocity, _, _)| {
+                other_transform.translation.xy() + other_velocity.0 * TARGET_LEAD
+            })
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            });
+
+        let surfacing = health.0 < tablet.max_health * bot.difficulty.surface_health();
+        let goal =
+            if position.x < water.left + WALL_MARGIN || position.x > water.right - WALL_MARGIN {
+                Vec2::new((water.left + water.right) / 2., position.y)
+            } else if surfacing || target.is_none() {
+                Vec2::new(position.x, water.surface_y(position.x) + WALL_MARGIN)
+            } else {
+                target.unwrap_or(position)
+            };
+
+        *intent = steer(
+            transform,
+            angular_velocity.0,
+            goal - position,
+            bot.difficulty,
+        );
+    }
+}
+
+// turn the tablet so its turbos push it toward `direction`
+fn steer(
+    transform: &Transform,
+    angular_velocity: f32,
+    direction: Vec2,
+    difficulty: BotDifficulty,
+) -> PlayerIntent {
+    let thrust = difficulty.thrust();
+    let tolerance = difficulty.aim_tolerance();
+    let heading = (transform.rotation * Vec3::Y).xy();
+    let angle = heading.angle_to(direction) - angular_velocity * SPIN_ANTICIPATION;
+
+    if angle.abs() < tolerance {
+        PlayerIntent {
+            up: thrust,
+            ..default()
+        }
+    } else if angle.abs() > PI - tolerance {
+        // the goal is behind: the top turbo pushes backward
+        PlayerIntent {
+            down: thrust,
+            ..default()
+        }
+    } else if angle > 0. {
+        // the left turbo spins the tablet counterclockwise
+        PlayerIntent {
+            left: thrust,
+            ..default()
+        }
+    } else {
+        PlayerIntent {
+            right: thrust,
+            ..default()
+        }
+    }
+}
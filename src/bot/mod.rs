@@ -0,0 +1 @@
+pub mod bot_plugin;
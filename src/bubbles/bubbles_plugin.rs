@@ -0,0 +1,175 @@
+use avian2d::prelude::{Collider, ColliderDensity, ExternalForce, LinearVelocity, RigidBody};
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    ecs::entity::{EntityMapper, MapEntities},
+    math::{Vec2, Vec3, Vec3Swizzles},
+    prelude::{
+        in_state, Commands, Component, Entity, IntoSystemConfigs, OnEnter, Query, Res, ResMut,
+        Resource, Time, Transform, With, Without,
+    },
+};
+
+use crate::{
+    game_tuning::game_tuning_plugin::GameTuning,
+    rollback::rollback_plugin::{RollbackApp, RollbackIds},
+    water::water_plugin::Water,
+    AppState, InGame, PauseState, SimulationSet, Volume,
+};
+
+// density of a bubble relative to the `ColliderDensity` of the tablets
+const BUBBLE_DENSITY: f32 = 0.1;
+
+/// Bubble emitted by the tablet of a `Player` index.
+#[derive(Component, Clone)]
+pub struct Bubble(pub usize);
+
+/// Cosmetic bubble moved without the physics engine, kept for reuse once popped.
+#[derive(Component, Clone)]
+pub struct BubbleParticle {
+    pub velocity: Vec2,
+    pub alive: bool,
+}
+
+/// Popped particles waiting for reuse, and how many bubbles of each kind are out.
+#[derive(Resource, Clone, Default)]
+pub struct BubblePool {
+    free: Vec<Entity>,
+    particles: usize,
+    colliding: usize,
+}
+
+impl MapEntities for BubblePool {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for entity in &mut self.free {
+            *entity = entity_mapper.map_entity(*entity);
+        }
+    }
+}
+
+// area of the disc, the same measure as `Tablet::base_volume`
+fn bubble_volume(radius: f32) -> f32 {
+    std::f32::consts::PI * radius * radius
+}
+
+/// Spawns a colliding bubble while the budget allows it, a cosmetic particle otherwise.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_bubble(
+    commands: &mut Commands,
+    pool: &mut BubblePool,
+    rollback_ids: &mut RollbackIds,
+    tuning: &GameTuning,
+    player: usize,
+    transform: Vec3,
+    direction: Vec3,
+    initial_speed: f32,
+    is_colliding: bool,
+) {
+    let radius = tuning.bubble_radius;
+    let velocity = direction.xy() * initial_speed;
+    if is_colliding && pool.colliding < tuning.max_colliding_bubbles {
+        pool.colliding += 1;
+        commands.spawn((
+            InGame,
+            rollback_ids.next(),
+            Bubble(player),
+            RigidBody::Dynamic,
+            Collider::circle(radius),
+            Volume(bubble_volume(radius)),
+            Transform::from_translation(transform),
+            ColliderDensity(BUBBLE_DENSITY),
+            LinearVelocity(velocity),
+            ExternalForce::default().with_persistence(false),
+        ));
+        return;
+    }
+
+    let particle = (
+        Bubble(player),
+        Transform::from_translation(transform),
+        BubbleParticle {
+            velocity,
+            alive: true,
+        },
+    );
+    if let Some(entity) = pool.free.pop() {
+        commands.entity(entity).insert(particle);
+    } else if pool.particles < tuning.max_bubble_particles {
+        pool.particles += 1;
+        commands.spawn((InGame, rollback_ids.next(), particle));
+    }
+}
+
+// bubbles popping at the surface ripple it
+pub fn try_kill_bubbles(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    mut water: ResMut<Water>,
+    mut pool: ResMut<BubblePool>,
+    colliding: Query<
+        (Entity, &Transform, &LinearVelocity),
+        (With<Bubble>, Without<BubbleParticle>),
+    >,
+    mut particles: Query<(Entity, &Transform, &mut BubbleParticle)>,
+) {
+    let volume = bubble_volume(tuning.bubble_radius);
+    let pop = |water: &mut Water, translation: Vec3, velocity: Vec2| {
+        if translation.y > water.surface_y(translation.x) {
+            water.splash_body(translation.x, volume, velocity.y * tuning.splash_scale);
+        }
+    };
+
+    for (entity, transform, velocity) in colliding.iter() {
+        if !water.contains(&transform.translation) {
+            pop(&mut water, transform.translation, velocity.0);
+            commands.entity(entity).despawn();
+            pool.colliding -= 1;
+        }
+    }
+    for (entity, transform, mut particle) in particles.iter_mut() {
+        if particle.alive && !water.contains(&transform.translation) {
+            pop(&mut water, transform.translation, particle.velocity);
+            particle.alive = false;
+            pool.free.push(entity);
+        }
+    }
+}
+
+// same buoyancy and drag as the colliding bubbles get from `drag_force`
+fn move_bubble_particles(
+    time: Res<Time>,
+    tuning: Res<GameTuning>,
+    mut query: Query<(&mut Transform, &mut BubbleParticle)>,
+) {
+    let rise = tuning.fluid_density * tuning.gravity / BUBBLE_DENSITY
+        - tuning.gravity * tuning.gravity_scale;
+    for (mut transform, mut particle) in &mut query {
+        if !particle.alive {
+            continue;
+        }
+        particle.velocity.y += rise * time.delta_secs();
+        particle.velocity *= 1. - tuning.drag_water_coefficient;
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.);
+    }
+}
+
+fn reset_pool(mut commands: Commands) {
+    commands.insert_resource(BubblePool::default());
+}
+
+pub struct BubblesPlugin;
+
+impl Plugin for BubblesPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<BubblePool>();
+        app.rollback_resource_with_entities::<BubblePool>()
+            .rollback_component::<Bubble>()
+            .rollback_component::<BubbleParticle>();
+        app.add_systems(OnEnter(AppState::InGame), reset_pool);
+        app.add_systems(
+            FixedUpdate,
+            move_bubble_particles
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
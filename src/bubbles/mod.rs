@@ -0,0 +1 @@
+pub mod bubbles_plugin;
@@ -1,83 +1,37 @@
-use bevy::{
-    color::{Color, Srgba},
-    math::Vec3,
-    prelude::KeyCode,
-};
+use bevy::{color::Srgba, prelude::KeyCode};
 
 use crate::PlayerKeyMap;
 
-pub const GRAVITY: f32 = 9.8;
-pub const GRAVITY_SCALE: f32 = 12.;
-pub const FLUID_DENSITY: f32 = 6.5; // Densité de l'eau en kg/m^3
-pub const DRAG_WATER_COEFFICIENT: f32 = 0.01;
-pub const DRAG_AIR_COEFFICIENT: f32 = 0.003;
-
-pub const CACHET_DENSITY: f32 = 10.;
-
-pub const BUBBLE_RADIUS: f32 = 2.;
-pub const BUBBLE_EMMISSION_SPEED: f32 = 100.;
-
-pub const TURBO_FORCE: f32 = 7500000. * 4.;
-pub const NB_TURBO_PARTICLE: usize = 5;
-
-// Camera
-pub const CAM_ELASTICITY: f32 = 0.95;
-pub const CAM_ZOOM_SPEED: f32 = 0.01;
-pub const CAM_ZOOM_MIN: f32 = 0.1; // zoom in
-pub const CAM_ZOOM_MAX: f32 = 20.; // zoom out
-pub const CAM_BUFFER: f32 = 0.15; // buffer pct
-
-// Arena
+// Default arena
 pub const GLASS_RADIUS: f32 = 800.;
 pub const GLASS_HEIGHT: f32 = 1600.;
 pub const WATER_LEVEL: f32 = 1400.;
 pub const GLASS_WIDTH: f32 = 30.;
 
-// Health
-pub const INITIAL_HEALTH: f32 = 1000.;
-pub const GLOBAL_DAMAGE_SCALE: f32 = 0.1;
-pub const WATER_TICK_DAMAGE: f32 = 2.;
-pub const TURBO_TICK_DAMAGE: f32 = -0.7;
-
 // END GAME MENU
-pub const MENU_DURATION : u32 = 300;
+pub const MENU_DURATION: u32 = 300;
 
 // PLAYER
-pub const PLAYER_COLOR: [Srgba; 4] = [
+pub const MAX_PLAYERS: usize = 4;
+
+// colours to pick from in the lobby, the first ones are the defaults of each slot
+pub const PLAYER_PALETTE: [Srgba; 8] = [
     bevy::color::palettes::css::ORANGE,
     bevy::color::palettes::css::LIGHT_PINK,
     bevy::color::palettes::css::LAVENDER,
     bevy::color::palettes::css::PALE_TURQUOISE,
+    bevy::color::palettes::css::LIGHT_GREEN,
+    bevy::color::palettes::css::GOLD,
+    bevy::color::palettes::css::SALMON,
+    bevy::color::palettes::css::LIGHT_SKY_BLUE,
 ];
 
-pub const PLAYER_POSITION: [[Vec3; 4]; 4] = [
-    [
-        Vec3::new(0., 0., 0.),
-        Vec3::new(0., 0., 0.),
-        Vec3::new(0., 0., 0.),
-        Vec3::new(0., 0., 0.),
-    ],
-    [
-        Vec3::new(-GLASS_RADIUS / 4.0, 0., 0.),
-        Vec3::new(GLASS_RADIUS / 4.0, 0., 0.),
-        Vec3::new(0., 0., 0.),
-        Vec3::new(0., 0., 0.),
-    ],
-    [
-        Vec3::new(-GLASS_RADIUS * 3. / 5.0, 0., 0.),
-        Vec3::new(0., 0., 0.),
-        Vec3::new(GLASS_RADIUS * 3. / 5.0, 0., 0.),
-        Vec3::new(0., 0., 0.),
-    ],
-    [
-        Vec3::new(-GLASS_RADIUS * 4. / 6.0, 0., 0.),
-        Vec3::new(-GLASS_RADIUS / 6.0, 0., 0.),
-        Vec3::new(GLASS_RADIUS / 6.0, 0., 0.),
-        Vec3::new(GLASS_RADIUS * 4. / 6.0, 0., 0.),
-    ],
+pub const TEAM_COLOR: [Srgba; 2] = [
+    bevy::color::palettes::css::TOMATO,
+    bevy::color::palettes::css::DODGER_BLUE,
 ];
 
-pub const PLAYER_CONTROL: [PlayerKeyMap; 4] = [
+pub const PLAYER_CONTROL: [PlayerKeyMap; MAX_PLAYERS] = [
     PlayerKeyMap {
         up: KeyCode::KeyW,
         left: KeyCode::KeyA,
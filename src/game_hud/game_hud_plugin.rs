@@ -1,28 +1,56 @@
 use bevy::{
-    app::{FixedPostUpdate, Plugin},
+    app::{FixedPostUpdate, Plugin, Update},
     color::Color,
     prelude::{
-        in_state, BuildChildren, ChildBuild, Commands, Entity, ImageNode, IntoSystemConfigs,
-        NextState, OnEnter, Query, Res, ResMut, With,
+        in_state, BuildChildren, ChildBuild, Children, Commands, Component, DespawnRecursiveExt,
+        Entity, EventReader, Has, ImageNode, IntoSystemConfigs, NextState, OnEnter, Query, Res,
+        ResMut, Text, TextSpan, Visibility, With,
     },
     sprite::{BorderRect, SliceScaleMode, TextureSlicer},
-    ui::{widget::NodeImageMode, AlignItems, FlexDirection, JustifyContent, Node, UiRect, Val},
+    text::{TextColor, TextFont},
+    time::{Time, Timer, TimerMode},
+    ui::{
+        widget::NodeImageMode, AlignItems, FlexDirection, JustifyContent, Node, PositionType,
+        UiRect, Val,
+    },
     utils::default,
 };
 
 use crate::{
-    AppState, EndGameDisplay, Health, HudInnerBar, HudPlayer, InGame, MainMenuState, Player,
-    PlayerNumber, SpriteAssets, INITIAL_HEALTH, MENU_DURATION, PLAYER_COLOR,
+    lobby::lobby_plugin::PlayerColors, netcode::netcode_plugin::ConfirmedEliminations,
+    pickup::pickup_plugin::TurboBoost, tablet::tablet_plugin::Tablet, AppState, EliminationCause,
+    EndGameDisplay, FontAssets, Health, HudInnerBar, HudPlayer, InGame, PauseState, Player,
+    PlayerEliminated, PlayerNumber, RoundOutcome, SpriteAssets, Teams, MENU_DURATION,
 };
 
+// seconds an elimination stays in the kill feed, and how many are shown at once
+const FEED_DURATION: f32 = 6.;
+const FEED_LENGTH: usize = 5;
+
+/// Shown next to the health bar of a tablet while its lemon boost lasts.
+#[derive(Component)]
+struct HudBoostIcon;
+
+/// Column of the last eliminations at the top of the screen.
+#[derive(Component)]
+struct KillFeed;
+
+#[derive(Component)]
+struct KillFeedEntry(Timer);
+
+/// Outcome the end of round banner was spawned for, it may still be rolled back in a LAN match.
+#[derive(Component)]
+struct ShownOutcome(RoundOutcome);
+
 pub struct GameHudPlugin;
 
 impl Plugin for GameHudPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(OnEnter(AppState::InGame), setup_ui);
+        app.add_systems(OnEnter(AppState::InGame), (setup_ui, setup_kill_feed));
+        app.add_systems(Update, update_kill_feed.run_if(in_state(AppState::InGame)));
         app.add_systems(
             FixedPostUpdate,
-            (update_ui, end_game_display).run_if(in_state(AppState::InGame)),
+            (update_ui, update_boost_icons, end_game_display).run_if(in_state(PauseState::Running)),
         );
     }
 }
@@ -30,18 +58,26 @@ impl Plugin for GameHudPlugin {
 fn end_game_display(
     mut commands: Commands,
     sprite_assets: Res<SpriteAssets>,
-    player_number: Res<PlayerNumber>,
-    query: Query<(&Health, &Player)>,
-    mut query_end_menu: Query<(Entity, &mut EndGameDisplay)>,
+    player_colors: Res<PlayerColors>,
+    teams: Res<Teams>,
+    font_assets: Res<FontAssets>,
+    round_outcome: Option<Res<RoundOutcome>>,
+    mut query_end_menu: Query<(Entity, &mut EndGameDisplay, &ShownOutcome)>,
     mut app_state: ResMut<NextState<AppState>>,
-    mut menu_state: ResMut<NextState<MainMenuState>>,
 ) {
-    for (entity, mut end_menu_display) in query_end_menu.iter_mut() {
+    // a restore of the confirmed world undid or changed the predicted outcome
+    for (entity, _, shown) in query_end_menu.iter() {
+        if round_outcome.as_deref() != Some(&shown.0) {
+            commands.entity(entity).despawn_recursive();
+            return;
+        }
+    }
+
+    for (entity, mut end_menu_display, _) in query_end_menu.iter_mut() {
         end_menu_display.0 -= 1;
         if end_menu_display.0 == 0 {
             commands.entity(entity).despawn();
-            app_state.set(AppState::MainMenu);
-            menu_state.set(MainMenuState::HomeMenu);
+            app_state.set(AppState::Scoreboard);
         }
     }
 
@@ -49,21 +85,11 @@ fn end_game_display(
         return;
     }
 
-    let mut last_player_id = 0;
-    let mut alive_players = 0;
-    for (health, player) in query.iter() {
-        if health.0 > 0. {
-            alive_players += 1;
-            last_player_id = player.0;
-        }
-    }
-
-    if (player_number.0 == 1 && alive_players <= 0) || (player_number.0 != 1 && alive_players <= 1)
-    {
-        let mut cup_file = &sprite_assets.cup;
-        if alive_players == 0 {
-            cup_file = &sprite_assets.cup_dead;
-        }
+    if let Some(round_outcome) = round_outcome {
+        let cup_file = match round_outcome.winner {
+            Some(_) => &sprite_assets.cup,
+            None => &sprite_assets.cup_dead,
+        };
         let image_cup = cup_file.clone();
 
         let files = [
@@ -73,8 +99,6 @@ fn end_game_display(
             &sprite_assets.p4_won,
         ];
 
-        let image_winner = files[last_player_id].clone();
-
         let slicer = TextureSlicer {
             border: BorderRect::square(64.0),
             center_scale_mode: SliceScaleMode::Stretch,
@@ -85,6 +109,7 @@ fn end_game_display(
             .spawn((
                 InGame,
                 EndGameDisplay(MENU_DURATION),
+                ShownOutcome(*round_outcome),
                 Node {
                     flex_direction: FlexDirection::Column,
                     width: Val::Percent(100.),
@@ -110,13 +135,25 @@ fn end_game_display(
                     },
                 ));
 
-                if alive_players >= 1 {
+                let winner = round_outcome.winner;
+                if let (Some(winner), true) = (winner, teams.is_team_match()) {
+                    parent.spawn((
+                        InGame,
+                        Text::new(format!("{} won", teams.side_label(winner))),
+                        TextFont {
+                            font: font_assets.bold.clone(),
+                            font_size: 64.0,
+                            ..default()
+                        },
+                        TextColor(player_colors.side(&teams, winner)),
+                    ));
+                } else if let Some(winner) = winner {
                     parent.spawn((
                         InGame,
                         ImageNode {
-                            image: image_winner,
+                            image: files[winner].clone(),
                             image_mode: NodeImageMode::Stretch,
-                            color: Color::from(PLAYER_COLOR[last_player_id]),
+                            color: player_colors.get(winner),
                             ..default()
                         },
                         Node {
@@ -131,14 +168,32 @@ fn end_game_display(
 }
 
 fn update_ui(
-    mut query_players: Query<(&Health, &Player)>,
+    mut query_players: Query<(&Health, &Tablet, &Player)>,
     mut query_ui_inner: Query<(&mut Node, &HudPlayer), With<HudInnerBar>>,
 ) {
-    for (health, player) in &mut query_players {
+    for (health, tablet, player) in &mut query_players {
         for (mut node, hudplayer) in &mut query_ui_inner {
             if hudplayer.0 == player.0 {
                 let min = 13.;
-                node.width = Val::Percent(min + (100. - min) * health.0.max(0.) / INITIAL_HEALTH);
+                node.width =
+                    Val::Percent(min + (100. - min) * health.0.max(0.) / tablet.max_health);
+            }
+        }
+    }
+}
+
+fn update_boost_icons(
+    query_players: Query<(&Player, Has<TurboBoost>)>,
+    mut query_icons: Query<(&mut Visibility, &HudPlayer), With<HudBoostIcon>>,
+) {
+    for (player, boosted) in &query_players {
+        for (mut visibility, hudplayer) in &mut query_icons {
+            if hudplayer.0 == player.0 {
+                *visibility = if boosted {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
             }
         }
     }
@@ -148,9 +203,12 @@ fn setup_ui(
     mut commands: Commands,
     asset_sprite: Res<SpriteAssets>,
     player_number: Res<PlayerNumber>,
+    player_colors: Res<PlayerColors>,
+    teams: Res<Teams>,
 ) {
     let image_outer_bar = asset_sprite.bar_outer.clone();
     let image_inner_bar = asset_sprite.bar_inner.clone();
+    let image_boost_icon = asset_sprite.mini_bubble.clone();
 
     let slicer = TextureSlicer {
         border: BorderRect::square(64.0),
@@ -174,6 +232,13 @@ fn setup_ui(
             let w = 20.0;
             let h = 32.0;
             for i in 0..player_number.0 {
+                let color = player_colors.get(i);
+                // the frame shows the team, the gauge the player
+                let frame_color = if teams.is_team_match() {
+                    player_colors.side(&teams, teams.team(i))
+                } else {
+                    color
+                };
                 parent
                     .spawn((
                         InGame,
@@ -181,7 +246,7 @@ fn setup_ui(
                         ImageNode {
                             image: image_outer_bar.clone(),
                             image_mode: NodeImageMode::Sliced(slicer.clone()),
-                            color: Color::from(PLAYER_COLOR[i]),
+                            color: frame_color,
                             ..default()
                         },
                         Node {
@@ -202,7 +267,7 @@ fn setup_ui(
                         ImageNode {
                             image: image_inner_bar.clone(),
                             image_mode: NodeImageMode::Sliced(slicer.clone()),
-                            color: Color::from(PLAYER_COLOR[i]),
+                            color,
                             ..default()
                         },
                         Node {
@@ -215,7 +280,125 @@ fn setup_ui(
                             // margin: UiRect::all(Val::Px(20.0)),
                             ..default()
                         },
+                    ))
+                    .with_child((
+                        InGame,
+                        HudBoostIcon,
+                        HudPlayer(i),
+                        Visibility::Hidden,
+                        ImageNode {
+                            image: image_boost_icon.clone(),
+                            color: Color::from(bevy::color::palettes::css::GOLD),
+                            ..default()
+                        },
+                        Node {
+                            position_type: PositionType::Absolute,
+                            right: Val::Px(-h - 8.),
+                            width: Val::Px(h),
+                            height: Val::Px(h),
+                            ..default()
+                        },
                     ));
             }
         });
 }
+
+fn setup_kill_feed(mut commands: Commands) {
+    commands.spawn((
+        InGame,
+        KillFeed,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Px(20.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.),
+            ..default()
+        },
+    ));
+}
+
+// parts of the line telling how `eliminated.player` went out, each in the colour of the player it names
+fn feed_line(eliminated: &PlayerEliminated, player_colors: &PlayerColors) -> Vec<(String, Color)> {
+    let name = |player: usize| (format!("Player {}", player + 1), player_colors.get(player));
+    let text = |text: &str| (text.to_string(), Color::WHITE);
+    let victim = name(eliminated.player);
+    match (eliminated.cause, eliminated.attacker) {
+        (EliminationCause::Dissolved, Some(attacker)) => {
+            vec![name(attacker), text(" dissolved "), victim]
+        }
+        (EliminationCause::Dissolved, None) => vec![victim, text(" dissolved")],
+        (EliminationCause::KnockedOut, Some(attacker)) => vec![
+            name(attacker),
+            text(" knocked "),
+            victim,
+            text(" out of the glass"),
+        ],
+        (EliminationCause::KnockedOut, None) => vec![victim, text(" fell out of the glass")],
+        (EliminationCause::SelfFizz, _) => vec![victim, text(" fizzed out on their own turbos")],
+    }
+}
+
+fn update_kill_feed(
+    mut commands: Commands,
+    time: Res<Time>,
+    font_assets: Res<FontAssets>,
+    player_colors: Res<PlayerColors>,
+    mut eliminations: EventReader<PlayerEliminated>,
+    confirmed_eliminations: Option<ResMut<ConfirmedEliminations>>,
+    feed_query: Query<(Entity, Option<&Children>), With<KillFeed>>,
+    mut entry_query: Query<&mut KillFeedEntry>,
+) {
+    let text_font = TextFont {
+        font: font_assets.bold.clone(),
+        font_size: 24.0,
+        ..default()
+    };
+    // a LAN round announces what every player agrees on, the predicted events may be rolled back
+    let new_eliminations: Vec<PlayerEliminated> = match confirmed_eliminations {
+        Some(mut confirmed) => {
+            eliminations.clear();
+            std::mem::take(&mut confirmed.0)
+        }
+        None => eliminations.read().copied().collect(),
+    };
+    for (feed, entries) in &feed_query {
+        let mut shown = 0;
+        // newest entries come last, room is left for the ones added this frame
+        for entry in entries.into_iter().flatten().rev() {
+            let Ok(mut timer) = entry_query.get_mut(*entry) else {
+                continue;
+            };
+            timer.0.tick(time.delta());
+            if timer.0.finished() || shown + new_eliminations.len() >= FEED_LENGTH {
+                commands.entity(*entry).despawn_recursive();
+            } else {
+                shown += 1;
+            }
+        }
+
+        let skipped = new_eliminations.len().saturating_sub(FEED_LENGTH);
+        for eliminated in new_eliminations.iter().skip(skipped) {
+            commands.entity(feed).with_children(|parent| {
+                parent
+                    .spawn((
+                        InGame,
+                        KillFeedEntry(Timer::from_seconds(FEED_DURATION, TimerMode::Once)),
+                        Text::default(),
+                        text_font.clone(),
+                    ))
+                    .with_children(|line| {
+                        for (part, color) in feed_line(eliminated, &player_colors) {
+                            line.spawn((
+                                InGame,
+                                TextSpan::new(part),
+                                text_font.clone(),
+                                TextColor(color),
+                            ));
+                        }
+                    });
+            });
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    color::Color,
+    prelude::{
+        in_state, BuildChildren, ChildBuild, Commands, Component, DespawnRecursiveExt, Entity,
+        ImageNode, IntoSystemConfigs, NextState, OnEnter, OnExit, Query, Res, ResMut, Resource,
+        Text, With,
+    },
+    text::{TextColor, TextFont},
+    ui::{widget::NodeImageMode, AlignItems, FlexDirection, JustifyContent, Node, UiRect, Val},
+    utils::default,
+};
+
+use crate::{
+    lobby::lobby_plugin::PlayerColors, AppState, FontAssets, MainMenuState, SpriteAssets, Teams,
+    MENU_DURATION,
+};
+
+/// Length of the series chosen in the player menu.
+#[derive(Resource)]
+pub struct MatchSettings {
+    pub best_of: u32,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings { best_of: 3 }
+    }
+}
+
+impl MatchSettings {
+    pub fn next(&self) -> MatchSettings {
+        MatchSettings {
+            best_of: if self.best_of >= 7 {
+                1
+            } else {
+                self.best_of + 2
+            },
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("Best of {}", self.best_of)
+    }
+}
+
+/// Rounds won by each team during the current match, a team per `Player` index outside of team matches.
+#[derive(Resource, Clone, Default)]
+pub struct MatchScore {
+    pub wins: Vec<u32>,
+    pub rounds_to_win: u32,
+}
+
+impl MatchScore {
+    pub fn new(side_count: usize, best_of: u32) -> Self {
+        MatchScore {
+            wins: vec![0; side_count],
+            rounds_to_win: best_of / 2 + 1,
+        }
+    }
+
+    pub fn add_win(&mut self, side: usize) {
+        if let Some(wins) = self.wins.get_mut(side) {
+            *wins += 1;
+        }
+    }
+
+    pub fn match_winner(&self) -> Option<usize> {
+        self.wins
+            .iter()
+            .position(|wins| *wins >= self.rounds_to_win)
+    }
+}
+
+#[derive(Component)]
+struct ScoreboardDisplay(u32);
+
+pub struct GameMatchPlugin;
+
+impl Plugin for GameMatchPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<MatchSettings>();
+        app.add_systems(OnEnter(AppState::Scoreboard), setup_scoreboard);
+        app.add_systems(
+            FixedUpdate,
+            scoreboard_countdown.run_if(in_state(AppState::Scoreboard)),
+        );
+        app.add_systems(OnExit(AppState::Scoreboard), despawn_scoreboard);
+    }
+}
+
+fn setup_scoreboard(
+    mut commands: Commands,
+    sprite_assets: Res<SpriteAssets>,
+    font_assets: Res<FontAssets>,
+    match_score: Res<MatchScore>,
+    player_colors: Res<PlayerColors>,
+    teams: Res<Teams>,
+) {
+    let text_font = TextFont {
+        font: font_assets.bold.clone(),
+        font_size: 48.0,
+        ..default()
+    };
+    let files = [
+        &sprite_assets.p1_won,
+        &sprite_assets.p2_won,
+        &sprite_assets.p3_won,
+        &sprite_assets.p4_won,
+    ];
+
+    commands
+        .spawn((
+            ScoreboardDisplay(MENU_DURATION),
+            Node {
+                flex_direction: FlexDirection::Column,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            let match_winner = match_score.match_winner();
+            if let (Some(winner), true) = (match_winner, teams.is_team_match()) {
+                parent.spawn((
+                    Text::new(format!("{} won", teams.side_label(winner))),
+                    text_font.clone(),
+                    TextColor(player_colors.side(&teams, winner)),
+                    Node {
+                        margin: UiRect::bottom(Val::Px(40.)),
+                        ..default()
+                    },
+                ));
+            } else if let Some(winner) = match_winner {
+                parent.spawn((
+                    ImageNode {
+                        image: files[winner].clone(),
+                        image_mode: NodeImageMode::Stretch,
+                        color: player_colors.get(winner),
+                        ..default()
+                    },
+                    Node {
+                        width: Val::Px(600.),
+                        aspect_ratio: Some(2.0),
+                        margin: UiRect::bottom(Val::Px(40.)),
+                        ..default()
+                    },
+                ));
+            } else {
+                parent.spawn((
+                    Text::new(format!("First to {} rounds", match_score.rounds_to_win)),
+                    text_font.clone(),
+                    TextColor(Color::WHITE),
+                ));
+            }
+
+            for (side, wins) in match_score.wins.iter().enumerate() {
+                parent.spawn((
+                    Text::new(format!(
+                        "{}   {} / {}",
+                        teams.side_label(side),
+                        wins,
+                        match_score.rounds_to_win
+                    )),
+                    text_font.clone(),
+                    TextColor(player_colors.side(&teams, side)),
+                ));
+            }
+        });
+}
+
+fn scoreboard_countdown(
+    mut query: Query<&mut ScoreboardDisplay>,
+    match_score: Res<MatchScore>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    for mut display in query.iter_mut() {
+        display.0 = display.0.saturating_sub(1);
+        if display.0 == 0 {
+            if match_score.match_winner().is_some() {
+                app_state.set(AppState::MainMenu);
+                menu_state.set(MainMenuState::HomeMenu);
+            } else {
+                app_state.set(AppState::InGame);
+            }
+        }
+    }
+}
+
+fn despawn_scoreboard(mut commands: Commands, query: Query<Entity, With<ScoreboardDisplay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
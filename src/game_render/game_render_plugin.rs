@@ -0,0 +1,372 @@
+use std::f32::consts::FRAC_PI_2;
+
+use avian2d::prelude::Collider;
+use bevy::{
+    app::{Plugin, Startup, Update},
+    asset::{AssetServer, Assets, RenderAssetUsages},
+    color::{Alpha, Color, Mix},
+    math::{
+        primitives::{Capsule2d, Circle, Rectangle},
+        Quat, Vec2, Vec3, Vec3Swizzles,
+    },
+    prelude::{
+        in_state, resource_equals, Added, Camera2d, Changed, ColorMaterial, Commands, Component,
+        DetectChanges, Entity, Handle, IntoSystemConfigs, IsDefaultUiCamera, Mesh, Mesh2d,
+        MeshMaterial2d, OnEnter, OrthographicProjection, Query, Res, ResMut, Resource, Sprite,
+        Transform, Visibility, With, Without,
+    },
+    render::mesh::{Indices, PrimitiveTopology},
+};
+
+use crate::{
+    arena::arena_plugin::Arena,
+    bubbles::bubbles_plugin::{Bubble, BubbleParticle},
+    cachet_material::CachetMaterial,
+    game_tuning::game_tuning_plugin::GameTuning,
+    hazard::hazard_plugin::{Spoon, Straw, STRAW_LENGTH},
+    lobby::lobby_plugin::PlayerColors,
+    pickup::pickup_plugin::{Pickup, PickupKind},
+    picture_in_picture::picture_in_picture_plugin::{main_area, PipCamera},
+    settings::settings_plugin::GameSettings,
+    spectator::spectator_plugin::CameraView,
+    water::water_plugin::{Water, SURFACE_MARGIN},
+    AppState, Glass, Health, InGame, Player, PlayerNumber, SpriteAssets,
+};
+
+/// Water drawn in front of and behind the tablets, sharing one deformable mesh.
+#[derive(Component)]
+struct WaterSurface;
+
+/// Mesh and per player material shared by every bubble, so they batch into few draws.
+#[derive(Resource)]
+struct BubbleLook {
+    mesh: Handle<Mesh>,
+    materials: Vec<Handle<ColorMaterial>>,
+}
+
+/// Everything only needed to look at a match: camera, meshes, materials and sprites.
+pub struct GameRenderPlugin;
+
+impl Plugin for GameRenderPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(Startup, setup);
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (resetup, setup_arena_visuals, setup_bubble_look),
+        );
+        app.add_systems(
+            Update,
+            (
+                dress_glasses,
+                dress_players,
+                dress_bubbles,
+                dress_pickups,
+                dress_hazards,
+                show_bubble_particles,
+                update_water_mesh,
+                update_camera.run_if(resource_equals(CameraView::Match)),
+            )
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// the heads-up display stays on the main camera when picture in picture views are added
+fn setup(mut commands: Commands) {
+    commands.spawn((Camera2d, IsDefaultUiCamera));
+}
+
+fn resetup(mut commands: Commands, query: Query<Entity, With<Camera2d>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((Camera2d, IsDefaultUiCamera));
+}
+
+// mesh matching the collider of a simulated entity
+fn collider_mesh(collider: &Collider) -> Option<Mesh> {
+    let shape = collider.shape();
+    if let Some(cuboid) = shape.as_cuboid() {
+        let half_size = Vec2::new(cuboid.half_extents.x, cuboid.half_extents.y);
+        Some(Rectangle::from_size(half_size * 2.).into())
+    } else if let Some(capsule) = shape.as_capsule() {
+        // the primitive capsule stands along y
+        let a = Vec2::new(capsule.segment.a.x, capsule.segment.a.y);
+        let b = Vec2::new(capsule.segment.b.x, capsule.segment.b.y);
+        let mesh: Mesh = Capsule2d::new(capsule.radius, a.distance(b)).into();
+        Some(
+            mesh.rotated_by(Quat::from_rotation_z((b - a).to_angle() - FRAC_PI_2))
+                .translated_by(((a + b) / 2.).extend(0.)),
+        )
+    } else {
+        shape.as_ball().map(|ball| Circle::new(ball.radius).into())
+    }
+}
+
+fn setup_arena_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    arena: Res<Arena>,
+    water: Res<Water>,
+) {
+    let mut water_color = Color::from(bevy::color::palettes::css::LIGHT_YELLOW);
+    water_color.set_alpha(0.1);
+
+    // Background
+    commands.spawn((
+        InGame,
+        Transform::from_translation(arena.background.position)
+            .with_scale(arena.background.scale.extend(1.)),
+        Sprite::from_image(asset_server.load(&arena.background.image)),
+    ));
+
+    // Arena
+    let water_mesh = meshes.add(water_mesh(&water));
+    let water_material = materials.add(water_color);
+    for z in [2., -1.] {
+        commands.spawn((
+            InGame,
+            WaterSurface,
+            Mesh2d(water_mesh.clone()),
+            MeshMaterial2d(water_material.clone()),
+            Transform::from_xyz(0., 0., z),
+        ));
+    }
+}
+
+// one quad per pair of surface columns, from the surface down to the bottom of the glass
+fn water_mesh(water: &Water) -> Mesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    let width = water.right - water.left;
+    for (i, point) in water.surface_points().enumerate() {
+        let u = (point.x - water.left) / width;
+        let surface = point.y + SURFACE_MARGIN;
+        // the water stops at the walls it rests on
+        positions.push([point.x, surface, 0.]);
+        positions.push([point.x, water.bottom_y(point.x).min(surface), 0.]);
+        uvs.push([u, 0.]);
+        uvs.push([u, 1.]);
+        if i > 0 {
+            let top = 2 * i as u32;
+            indices.extend([top - 2, top - 1, top + 1, top - 2, top + 1, top]);
+        }
+    }
+    let normals = vec![[0., 0., 1.]; positions.len()];
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+fn update_water_mesh(
+    water: Res<Water>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<&Mesh2d, With<WaterSurface>>,
+) {
+    if !water.is_changed() {
+        return;
+    }
+    // both layers share the mesh, it only needs one update
+    if let Some(mesh) = query.iter().next() {
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = water_mesh(&water);
+        }
+    }
+}
+
+fn dress_glasses(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &Collider), Added<Glass>>,
+) {
+    let mut glass_color = Color::from(bevy::color::palettes::css::LIGHT_BLUE);
+    glass_color.set_alpha(0.3);
+
+    for (entity, collider) in query.iter() {
+        if let Some(mesh) = collider_mesh(collider) {
+            commands.entity(entity).insert((
+                Mesh2d(meshes.add(mesh)),
+                MeshMaterial2d(materials.add(glass_color)),
+            ));
+        }
+    }
+}
+
+fn dress_players(
+    mut commands: Commands,
+    sprite_assets: Res<SpriteAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CachetMaterial>>,
+    player_colors: Res<PlayerColors>,
+    query: Query<(Entity, &Player, &Collider), Added<Player>>,
+) {
+    for (entity, player, collider) in query.iter() {
+        if let Some(mesh) = collider_mesh(collider) {
+            commands.entity(entity).insert((
+                Mesh2d(meshes.add(mesh)),
+                MeshMaterial2d(materials.add(CachetMaterial {
+                    color: player_colors.get(player.0).to_linear(),
+                    color_texture: Some(sprite_assets.cachet.clone()),
+                })),
+            ));
+        }
+    }
+}
+
+fn dress_pickups(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &Pickup, &Collider), Added<Pickup>>,
+) {
+    for (entity, pickup, collider) in query.iter() {
+        let color = match pickup.kind {
+            PickupKind::SugarCube => Color::WHITE,
+            PickupKind::LemonSlice => Color::from(bevy::color::palettes::css::GOLD),
+            PickupKind::IceCube => {
+                Color::from(bevy::color::palettes::css::LIGHT_CYAN).with_alpha(0.7)
+            }
+        };
+        if let Some(mesh) = collider_mesh(collider) {
+            commands.entity(entity).insert((
+                Mesh2d(meshes.add(mesh)),
+                MeshMaterial2d(materials.add(color)),
+            ));
+        }
+    }
+}
+
+fn dress_hazards(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    spoons: Query<Entity, Added<Spoon>>,
+    straws: Query<Entity, Added<Straw>>,
+) {
+    for entity in spoons.iter() {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(Rectangle::new(tuning.spoon_radius * 2., 24.))),
+            MeshMaterial2d(materials.add(Color::from(bevy::color::palettes::css::SILVER))),
+        ));
+    }
+    for entity in straws.iter() {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(Rectangle::new(48., STRAW_LENGTH))),
+            MeshMaterial2d(
+                materials.add(Color::from(bevy::color::palettes::css::HOT_PINK).with_alpha(0.8)),
+            ),
+        ));
+    }
+}
+
+// the radius and colours change between matches, so the look is rebuilt every round
+fn setup_bubble_look(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    player_number: Res<PlayerNumber>,
+    player_colors: Res<PlayerColors>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(BubbleLook {
+        mesh: meshes.add(Circle::new(tuning.bubble_radius)),
+        materials: (0..player_number.0)
+            .map(|player| {
+                materials.add(
+                    player_colors
+                        .get(player)
+                        .mix(&Color::from(bevy::color::palettes::css::WHITE), 0.5),
+                )
+            })
+            .collect(),
+    });
+}
+
+// pooled particles change owner when reused
+fn dress_bubbles(
+    mut commands: Commands,
+    look: Res<BubbleLook>,
+    query: Query<(Entity, &Bubble), Changed<Bubble>>,
+) {
+    for (entity, bubble) in query.iter() {
+        commands.entity(entity).insert((
+            Mesh2d(look.mesh.clone()),
+            MeshMaterial2d(look.materials[bubble.0].clone()),
+        ));
+    }
+}
+
+fn show_bubble_particles(
+    mut query: Query<(&BubbleParticle, &mut Visibility), Changed<BubbleParticle>>,
+) {
+    for (particle, mut visibility) in &mut query {
+        *visibility = if particle.alive {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_camera(
+    tuning: Res<GameTuning>,
+    settings: Res<GameSettings>,
+    arena: Res<Arena>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<Camera2d>, Without<Player>, Without<PipCamera>),
+    >,
+    player_query: Query<(&Transform, &Health), (With<Player>, Without<Camera2d>)>,
+) {
+    // arena center participation
+    let mut interest_area = arena.bounds;
+
+    // players participation, the ones far away have their own view when picture in picture is on
+    let main_area = main_area(&arena);
+    for (player_transform, health) in player_query.iter() {
+        let far = !main_area.contains(player_transform.translation.xy());
+        if health.0 > 0. && !(settings.picture_in_picture && far) {
+            interest_area = interest_area.union_point(player_transform.translation.xy());
+        }
+    }
+
+    // buffer
+    interest_area = interest_area.inflate(interest_area.size().max_element() * tuning.cam_buffer);
+    let center = interest_area.center();
+    let target_position = Vec3::new(center.x, center.y, 0.);
+
+    for (mut camera_transform, mut cam) in &mut camera_query {
+        let new_camera_translate = tuning.cam_elasticity * camera_transform.translation
+            + (1.0 - tuning.cam_elasticity) * target_position;
+        camera_transform.translation = new_camera_translate;
+
+        let mut cam_area = cam.area;
+        cam_area.min += new_camera_translate.xy();
+        cam_area.max += new_camera_translate.xy();
+
+        let mut zoom: f32 = cam.scale;
+
+        if cam_area.union(interest_area) != cam_area {
+            zoom *= 1. + tuning.cam_zoom_speed;
+        }
+
+        let inner = cam_area.inflate(-200.);
+        if inner.union(interest_area) == inner {
+            zoom *= 1. - tuning.cam_zoom_speed;
+        }
+
+        zoom = zoom.clamp(tuning.cam_zoom_min, tuning.cam_zoom_max);
+        cam.scale = zoom;
+    }
+}
@@ -0,0 +1,183 @@
+use avian2d::prelude::Gravity;
+use bevy::{
+    app::{Plugin, Update},
+    asset::{Asset, AssetEvent, Assets, Handle},
+    log::info,
+    math::Vec2,
+    prelude::{
+        not, resource_exists, DetectChanges, EventReader, IntoSystemConfigs, Res, ResMut, Resource,
+    },
+    reflect::TypePath,
+};
+use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_common_assets::ron::RonAssetPlugin;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::replay_plugin::ReplayPlayback;
+
+/// Physics and balance values, loaded from `assets/tuning/game.tuning.ron`.
+/// The file is watched on native builds, so edits apply to the running match.
+/// Fields missing from the file keep their default value.
+#[derive(Asset, Resource, TypePath, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GameTuning {
+    pub gravity: f32,
+    pub gravity_scale: f32,
+    pub fluid_density: f32, // Densité de l'eau en kg/m^3
+    pub drag_water_coefficient: f32,
+    pub drag_air_coefficient: f32,
+
+    pub cachet_density: f32,
+    pub dissolved_density: f32, // density of a tablet about to run out of health
+    pub min_tablet_scale: f32,  // size of a tablet about to run out of health
+
+    pub bubble_radius: f32,
+    pub bubble_emmission_speed: f32,
+    pub max_colliding_bubbles: usize,
+    pub max_bubble_particles: usize,
+
+    pub turbo_force: f32,
+    pub nb_turbo_particle: usize,
+
+    // Camera
+    pub cam_elasticity: f32,
+    pub cam_zoom_speed: f32,
+    pub cam_zoom_min: f32, // zoom in
+    pub cam_zoom_max: f32, // zoom out
+    pub cam_buffer: f32,   // buffer pct
+
+    // Health
+    pub initial_health: f32,
+    pub global_damage_scale: f32,
+    pub water_tick_damage: f32,
+    pub turbo_tick_damage: f32,
+
+    // Pickups, durations in fixed ticks
+    pub pickup_interval: u32,
+    pub pickup_lifetime: u32,
+    pub sugar_heal: f32,
+    pub lemon_duration: u32,
+    pub lemon_turbo_scale: f32,
+    pub ice_density: f32,
+
+    // Hazards, durations in fixed ticks
+    pub drain_speed: f32, // drop of the water level per tick
+    pub drain_min_depth: f32,
+    pub spoon_interval: u32,
+    pub spoon_duration: u32,
+    pub spoon_radius: f32,
+    pub spoon_acceleration: f32,
+    pub straw_radius: f32,
+    pub straw_acceleration: f32,
+
+    // Water surface
+    pub wave_stiffness: f32,
+    pub wave_damping: f32,
+    pub wave_spread: f32,
+    pub splash_scale: f32,
+    pub wave_push: f32,
+}
+
+impl Default for GameTuning {
+    fn default() -> Self {
+        GameTuning {
+            gravity: 9.8,
+            gravity_scale: 12.,
+            fluid_density: 6.5,
+            drag_water_coefficient: 0.01,
+            drag_air_coefficient: 0.003,
+            cachet_density: 10.,
+            dissolved_density: 4.,
+            min_tablet_scale: 0.4,
+            bubble_radius: 2.,
+            bubble_emmission_speed: 100.,
+            max_colliding_bubbles: 64,
+            max_bubble_particles: 2000,
+            turbo_force: 7500000. * 4.,
+            nb_turbo_particle: 5,
+            cam_elasticity: 0.95,
+            cam_zoom_speed: 0.01,
+            cam_zoom_min: 0.1,
+            cam_zoom_max: 20.,
+            cam_buffer: 0.15,
+            initial_health: 1000.,
+            global_damage_scale: 0.1,
+            water_tick_damage: 2.,
+            turbo_tick_damage: -0.7,
+            pickup_interval: 640,
+            pickup_lifetime: 960,
+            sugar_heal: 150.,
+            lemon_duration: 320,
+            lemon_turbo_scale: 1.5,
+            ice_density: 30.,
+            drain_speed: 0.15,
+            drain_min_depth: 200.,
+            spoon_interval: 768,
+            spoon_duration: 256,
+            spoon_radius: 350.,
+            spoon_acceleration: 600.,
+            straw_radius: 250.,
+            straw_acceleration: 500.,
+            wave_stiffness: 0.02,
+            wave_damping: 0.03,
+            wave_spread: 0.03,
+            splash_scale: 0.02,
+            wave_push: 40.,
+        }
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct TuningAssets {
+    #[asset(path = "tuning/game.tuning.ron")]
+    pub game: Handle<GameTuning>,
+}
+
+pub struct GameTuningPlugin;
+
+impl Plugin for GameTuningPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_plugins(RonAssetPlugin::<GameTuning>::new(&["tuning.ron"]));
+        app.init_resource::<GameTuning>();
+        // a replay keeps the tuning it was recorded with
+        app.add_systems(
+            Update,
+            (
+                apply_game_tuning.run_if(not(resource_exists::<ReplayPlayback>)),
+                update_gravity,
+            ),
+        );
+    }
+}
+
+// copy the tuning asset into the resource once loaded and every time the file is edited
+fn apply_game_tuning(
+    mut events: EventReader<AssetEvent<GameTuning>>,
+    tuning_assets: Option<Res<TuningAssets>>,
+    assets: Res<Assets<GameTuning>>,
+    mut tuning: ResMut<GameTuning>,
+) {
+    let Some(tuning_assets) = tuning_assets else {
+        return;
+    };
+    let id = tuning_assets.game.id();
+    let mut reload = tuning_assets.is_added();
+    for event in events.read() {
+        if let AssetEvent::Modified { id: modified } = event {
+            reload |= *modified == id;
+        }
+    }
+
+    if reload {
+        if let Some(new_tuning) = assets.get(id) {
+            info!("Game tuning applied");
+            *tuning = new_tuning.clone();
+        }
+    }
+}
+
+fn update_gravity(tuning: Res<GameTuning>, mut gravity: ResMut<Gravity>) {
+    if tuning.is_changed() {
+        gravity.0 = Vec2::NEG_Y * tuning.gravity * tuning.gravity_scale;
+    }
+}
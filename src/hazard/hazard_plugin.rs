@@ -0,0 +1,212 @@
+use avian2d::prelude::{ComputedMass, ExternalForce};
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    math::{Quat, Vec2, Vec3, Vec3Swizzles},
+    prelude::{
+        in_state, Commands, Component, Entity, IntoSystemConfigs, OnEnter, Query, Res, ResMut,
+        Resource, Transform, Without,
+    },
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::arena_plugin::Arena,
+    drag_force,
+    game_tuning::game_tuning_plugin::GameTuning,
+    rollback::rollback_plugin::{RollbackApp, RollbackIds},
+    update_health, use_turbo,
+    water::water_plugin::{step_water, Water},
+    AppState, GameRng, InGame, PauseState, SimulationSet,
+};
+
+/// Length of the straw, drawn from its mouth upward.
+pub const STRAW_LENGTH: f32 = 2000.;
+// depth of the straw mouth under the water at rest
+const STRAW_DEPTH: f32 = 250.;
+// distance kept from the walls when placing the spoon or the straw
+const HAZARD_MARGIN: f32 = 200.;
+// turns of the spoon per tick while stirring
+const SPOON_SPIN: f32 = 0.08;
+
+/// Hazards enabled for the current match.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hazards {
+    pub draining_water: bool,
+    pub spoon: bool,
+    pub straw: bool,
+}
+
+/// Hazards picked in the player menu, copied into `Hazards` when a match starts.
+#[derive(Resource, Default)]
+pub struct HazardSettings(pub Hazards);
+
+pub fn toggle_label(name: &str, enabled: bool) -> String {
+    format!("{name}: {}", if enabled { "On" } else { "Off" })
+}
+
+/// Stirs the water around it for `ticks_left` more ticks.
+#[derive(Component, Clone)]
+pub struct Spoon {
+    pub ticks_left: u32,
+}
+
+/// Sucks what floats near its `mouth` up the tube.
+#[derive(Component, Clone)]
+pub struct Straw {
+    pub mouth: Vec2,
+}
+
+/// Ticks until the spoon stirs again.
+#[derive(Resource, Clone, Default)]
+struct SpoonTimer {
+    cooldown: u32,
+}
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<Hazards>();
+        app.init_resource::<HazardSettings>();
+        app.init_resource::<SpoonTimer>();
+        app.rollback_resource::<SpoonTimer>()
+            .rollback_component::<Spoon>()
+            .rollback_component::<Straw>();
+        app.add_systems(OnEnter(AppState::InGame), reset_spoon_timer);
+        app.add_systems(
+            FixedUpdate,
+            (
+                drain_water
+                    .after(step_water)
+                    .before(use_turbo)
+                    .run_if(|hazards: Res<Hazards>| hazards.draining_water),
+                (
+                    stir_spoon.run_if(|hazards: Res<Hazards>| hazards.spoon),
+                    suck_straw.run_if(|hazards: Res<Hazards>| hazards.straw),
+                )
+                    .chain()
+                    .after(drag_force)
+                    .before(update_health),
+            )
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+fn reset_spoon_timer(mut commands: Commands, tuning: Res<GameTuning>) {
+    commands.insert_resource(SpoonTimer {
+        cooldown: tuning.spoon_interval,
+    });
+}
+
+// horizontal position away from the walls
+fn random_x(game_rng: &mut GameRng, arena: &Arena) -> f32 {
+    let left = arena.water.left + HAZARD_MARGIN;
+    let right = (arena.water.right - HAZARD_MARGIN).max(left);
+    game_rng.rng.random_range(left..=right)
+}
+
+// the water never drains below `drain_min_depth`, so the round can still be decided by fizzing
+fn drain_water(tuning: Res<GameTuning>, mut water: ResMut<Water>) {
+    let lowest = water.bottom + tuning.drain_min_depth;
+    if water.level > lowest {
+        water.level = (water.level - tuning.drain_speed).max(lowest);
+    }
+}
+
+// the spoon rests between stirs, then spins in a random spot and drags the water around it
+#[allow(clippy::type_complexity)]
+fn stir_spoon(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    water: Res<Water>,
+    mut game_rng: ResMut<GameRng>,
+    mut timer: ResMut<SpoonTimer>,
+    mut rollback_ids: ResMut<RollbackIds>,
+    mut spoons: Query<(Entity, &mut Spoon, &mut Transform)>,
+    mut bodies: Query<(&Transform, &ComputedMass, &mut ExternalForce), Without<Spoon>>,
+) {
+    let Ok((entity, mut spoon, mut spoon_transform)) = spoons.get_single_mut() else {
+        timer.cooldown = timer.cooldown.saturating_sub(1);
+        if timer.cooldown == 0 {
+            timer.cooldown = tuning.spoon_interval;
+            let x = random_x(&mut game_rng, &arena);
+            commands.spawn((
+                InGame,
+                rollback_ids.next(),
+                Spoon {
+                    ticks_left: tuning.spoon_duration,
+                },
+                Transform::from_translation(Vec3::new(x, (water.level + water.bottom) / 2., 1.)),
+            ));
+        }
+        return;
+    };
+
+    spoon.ticks_left = spoon.ticks_left.saturating_sub(1);
+    if spoon.ticks_left == 0 {
+        commands.entity(entity).despawn();
+        return;
+    }
+    spoon_transform.rotate(Quat::from_rotation_z(SPOON_SPIN));
+
+    let center = spoon_transform.translation.xy();
+    for (transform, mass, mut force) in &mut bodies {
+        let offset = transform.translation.xy() - center;
+        let distance = offset.length();
+        if distance < 1.
+            || distance > tuning.spoon_radius
+            || !water.contains(&transform.translation)
+        {
+            continue;
+        }
+        let falloff = 1. - distance / tuning.spoon_radius;
+        let swirl = offset.perp() / distance;
+        force.apply_force(swirl * tuning.spoon_acceleration * falloff * mass.value());
+    }
+}
+
+// the straw is placed on the first tick, so a replayed round draws the same spot
+#[allow(clippy::type_complexity)]
+fn suck_straw(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    water: Res<Water>,
+    mut game_rng: ResMut<GameRng>,
+    mut rollback_ids: ResMut<RollbackIds>,
+    straws: Query<&Straw>,
+    mut bodies: Query<(&Transform, &ComputedMass, &mut ExternalForce), Without<Straw>>,
+) {
+    let Ok(straw) = straws.get_single() else {
+        let mouth = Vec2::new(
+            random_x(&mut game_rng, &arena),
+            arena.water.level - STRAW_DEPTH,
+        );
+        commands.spawn((
+            InGame,
+            rollback_ids.next(),
+            Straw { mouth },
+            Transform::from_translation((mouth + Vec2::Y * STRAW_LENGTH / 2.).extend(1.)),
+        ));
+        return;
+    };
+
+    for (transform, mass, mut force) in &mut bodies {
+        let offset = straw.mouth - transform.translation.xy();
+        let distance = offset.length();
+        if distance < 1.
+            || distance > tuning.straw_radius
+            || !water.contains(&transform.translation)
+        {
+            continue;
+        }
+        let falloff = 1. - distance / tuning.straw_radius;
+        // up the straw weighs more than towards the mouth, so a body right above it is still lifted
+        let pull = offset / distance * 0.4 + Vec2::Y * 0.6;
+        force.apply_force(pull * tuning.straw_acceleration * falloff * mass.value());
+    }
+}
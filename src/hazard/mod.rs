@@ -0,0 +1 @@
+pub mod hazard_plugin;
@@ -1,34 +1,87 @@
 #![allow(clippy::type_complexity)]
+use arena::arena_plugin::{Arena, ArenaAssets, ArenaPlugin, ArenaWall};
 use bevy::{
+    app::ScheduleRunnerPlugin,
     asset::{AssetMetaCheck, Assets},
+    log::LogPlugin,
     prelude::*,
-    reflect::GetTupleField,
-    render::render_resource::ShaderRef,
     sprite::Material2dPlugin,
-    window::{PresentMode, WindowResolution},
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    window::WindowResolution,
 };
 use bevy_asset_loader::{
     asset_collection::AssetCollection,
     loading_state::{config::ConfigureLoadingState, LoadingState, LoadingStateAppExt},
 };
 use bevy_kira_audio::prelude::*;
+use bot::bot_plugin::{Bot, BotPlayers, BotPlugin, BotSettings};
+use bubbles::bubbles_plugin::{spawn_bubble, try_kill_bubbles, BubblePool, BubblesPlugin};
 use cachet_material::CachetMaterial;
 use game_hud::game_hud_plugin::GameHudPlugin;
+use game_match::game_match_plugin::GameMatchPlugin;
+pub use game_match::game_match_plugin::MatchScore;
+use game_render::game_render_plugin::GameRenderPlugin;
+pub use game_tuning::game_tuning_plugin::GameTuning;
+use game_tuning::game_tuning_plugin::{GameTuningPlugin, TuningAssets};
+use hazard::hazard_plugin::HazardPlugin;
+use lobby::lobby_plugin::LobbyPlugin;
 use main_menu::main_menu_plugin::MainMenuPlugin;
+pub use netcode::netcode_plugin::{confirmed_world, NetConfig, NetSession, NetcodePlugin};
+pub use server::server_plugin::{
+    server_app, ClientMessage, MatchSetup, PickupState, ServerConfig, ServerMessage, ServerPlugin,
+    Snapshot, TabletState,
+};
+pub use thin_client::thin_client_plugin::{server_address, ThinClient, ThinClientPlugin};
 
 use avian2d::prelude::*;
+mod arena;
+mod bot;
+mod bubbles;
 mod cachet_material;
 mod constants;
 mod game_hud;
+mod game_match;
+mod game_render;
+mod game_tuning;
+mod hazard;
+mod lobby;
 mod main_menu;
 mod my_audio;
+mod netcode;
 mod on_hit;
+mod pause_menu;
+mod persistence;
+mod pickup;
+mod picture_in_picture;
+mod player_input;
+mod replay;
+mod rollback;
+mod server;
+mod settings;
+mod spectator;
+mod tablet;
+mod thin_client;
+mod water;
 
 use constants::*;
 use my_audio::my_audio_plugin::{AudioAssets, MyAudioPlugin};
 use on_hit::on_hit_plugin::OnHitPlugin;
-use rand::Rng;
+use pause_menu::pause_menu_plugin::PauseMenuPlugin;
+use pickup::pickup_plugin::{PickupPlugin, TurboBoost};
+use picture_in_picture::picture_in_picture_plugin::PictureInPicturePlugin;
+use player_input::player_input_plugin::PlayerInputPlugin;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use replay::replay_plugin::ReplayPlugin;
+use rollback::rollback_plugin::{RollbackApp, RollbackIds, RollbackPlugin};
+use serde::{Deserialize, Serialize};
+use settings::settings_plugin::{GameSettings, SettingsPlugin};
+use spectator::spectator_plugin::SpectatorPlugin;
+pub use tablet::tablet_plugin::{PlayerTablets, TabletShape, TabletType};
+use tablet::tablet_plugin::{Tablet, TabletAssets, TabletPlugin};
+use water::water_plugin::{Water, WaterContact, WaterPlugin};
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PlayerKeyMap {
     up: KeyCode,
     left: KeyCode,
@@ -37,13 +90,33 @@ pub struct PlayerKeyMap {
 }
 
 #[derive(Resource)]
-pub struct PlayerNumber(usize);
+pub struct PlayerNumber(pub usize);
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct InGame;
 
-#[derive(Component)]
-struct Player(usize);
+#[derive(Component, Clone)]
+pub struct Player(pub usize);
+
+/// Thrust requested for each turbo of a tablet, from 0 (idle) to 1 (full force).
+#[derive(Component, Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerIntent {
+    pub up: f32,
+    pub left: f32,
+    pub right: f32,
+    pub down: f32,
+}
+
+impl PlayerIntent {
+    pub fn max(self, other: PlayerIntent) -> PlayerIntent {
+        PlayerIntent {
+            up: self.up.max(other.up),
+            left: self.left.max(other.left),
+            right: self.right.max(other.right),
+            down: self.down.max(other.down),
+        }
+    }
+}
 
 #[derive(Component, Copy, Clone)]
 struct HudPlayer(usize);
@@ -54,16 +127,136 @@ struct HudInnerBar;
 #[derive(Component, Copy, Clone)]
 struct EndGameDisplay(u32);
 
-#[derive(Component, Debug)]
-struct Health(f32);
+/// Set once the current round is decided, `winner` is `None` when every tablet dissolved.
+/// The winner is a team, which is the `Player` index outside of team matches.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RoundOutcome {
+    pub winner: Option<usize>,
+}
+
+/// How a tablet went out of the round.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EliminationCause {
+    /// Its health ran out to the water, the glass or a clash.
+    Dissolved,
+    /// It fell below the glass.
+    KnockedOut,
+    /// Its own turbos burnt the last of its health.
+    SelfFizz,
+}
+
+/// Sent once when a tablet goes out of the round.
+/// `attacker` is the last player whose tablet damaged it in a clash, if any did this round.
+#[derive(Event, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PlayerEliminated {
+    pub player: usize,
+    pub cause: EliminationCause,
+    pub attacker: Option<usize>,
+}
+
+/// Last player whose tablet damaged this one in a clash.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LastAttacker(pub Option<usize>);
+
+/// What can take health from a tablet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageSource {
+    Water,
+    Turbo,
+    Clash,
+    Glass,
+}
+
+/// Last source that damaged this tablet while it still had health, so what finished it off.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LastDamage(pub Option<DamageSource>);
+
+/// Side a tablet plays for, the last team standing wins the round.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Team(pub usize);
+
+/// Team of each `Player` index, an empty list is a free for all where everyone plays alone.
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Teams {
+    pub teams: Vec<usize>,
+    /// Whether teammates are hurt when they clash.
+    pub friendly_fire: bool,
+}
+
+impl Teams {
+    pub fn is_team_match(&self) -> bool {
+        !self.teams.is_empty()
+    }
 
-#[derive(Component)]
-struct Bubble;
+    pub fn team(&self, player: usize) -> usize {
+        self.teams.get(player).copied().unwrap_or(player)
+    }
 
-#[derive(Component)]
+    pub fn side_label(&self, side: usize) -> String {
+        if self.is_team_match() {
+            format!("Team {}", side + 1)
+        } else {
+            format!("P{}", side + 1)
+        }
+    }
+
+    /// Number of scores kept in a match, one per team or one per player.
+    pub fn side_count(&self, player_number: usize) -> usize {
+        if self.is_team_match() {
+            self.teams.iter().max().map_or(0, |team| team + 1)
+        } else {
+            player_number
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Health(pub f32);
+
+impl Health {
+    /// Takes `amount` of health, a negative amount heals and is not remembered as damage.
+    pub fn damage(&mut self, amount: f32, source: DamageSource, last_damage: &mut LastDamage) {
+        if amount > 0. && self.0 > 0. {
+            last_damage.0 = Some(source);
+        }
+        self.0 -= amount;
+    }
+}
+
+/// Only source of randomness of the simulation, reseeded every round so a replay can reproduce it.
+#[derive(Resource, Clone)]
+pub struct GameRng {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::from_seed(0)
+    }
+}
+
+/// Seed of the next round when every machine playing it must agree on it, as in a LAN match.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RoundSeed(pub u64);
+
+#[derive(Component, Clone)]
 struct Glass;
 
-#[derive(Component)]
+/// Tablet whose health ran out, shrinking until it is despawned.
+#[derive(Component, Clone)]
+struct Dissolved;
+
+#[derive(Component, Clone)]
 struct Volume(f32);
 
 #[derive(States, Debug, Clone, PartialEq, Default, Eq, Hash)]
@@ -72,6 +265,20 @@ pub enum AppState {
     AssetLoading,
     MainMenu,
     InGame,
+    // passes straight back to `InGame`, so a round can be replayed from scratch
+    RestartRound,
+    Scoreboard,
+}
+
+/// Only exists during a round, gameplay systems run while it is `Running`.
+#[derive(SubStates, Debug, Clone, PartialEq, Default, Eq, Hash)]
+#[source(AppState = AppState::InGame)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+    Settings,
+    Controls,
 }
 
 #[derive(States, Debug, Clone, PartialEq, Default, Eq, Hash)]
@@ -80,239 +287,169 @@ enum MainMenuState {
     HomeMenu,
     Help,
     Credit,
+    Controls,
+    Settings,
     PlayerMenu,
+    Lobby,
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn(Camera2d);
-}
-
-fn resetup(mut commands: Commands, query: Query<Entity, With<Camera2d>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
+fn setup_glasses(mut commands: Commands, arena: Res<Arena>, mut rollback_ids: ResMut<RollbackIds>) {
+    let mut spawn_wall = |collider: Collider, transform: Transform| {
+        commands.spawn((
+            InGame,
+            rollback_ids.next(),
+            RigidBody::Static,
+            collider,
+            transform,
+            Glass,
+        ));
+    };
+    for wall in &arena.walls {
+        match wall {
+            ArenaWall::Rectangle { center, size } => spawn_wall(
+                Collider::rectangle(size.x, size.y),
+                Transform::from_translation(center.extend(0.)),
+            ),
+            ArenaWall::Polyline { points, thickness } => {
+                for segment in points.windows(2) {
+                    let direction = segment[1] - segment[0];
+                    spawn_wall(
+                        Collider::rectangle(direction.length(), *thickness),
+                        Transform::from_translation(((segment[0] + segment[1]) / 2.).extend(0.))
+                            .with_rotation(Quat::from_rotation_z(direction.to_angle())),
+                    );
+                }
+                // round joints close the gaps between segments
+                for point in points.iter().skip(1).take(points.len().saturating_sub(2)) {
+                    spawn_wall(
+                        Collider::circle(thickness / 2.),
+                        Transform::from_translation(point.extend(0.)),
+                    );
+                }
+            }
+        }
     }
-    commands.spawn(Camera2d);
-}
-
-fn setup_glasses(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    sprite_assets: Res<SpriteAssets>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    let mut water_color = Color::from(bevy::color::palettes::css::LIGHT_YELLOW);
-    water_color.set_alpha(0.1);
-    let mut glass_color = Color::from(bevy::color::palettes::css::LIGHT_BLUE);
-    glass_color.set_alpha(0.3);
-
-    // Background
-    commands.spawn((
-        InGame,
-        Transform::default()
-            .with_translation(Vec3::new(0., 1000., -2.))
-            .with_scale(Vec3::new(2., 2., 1.)),
-        Sprite::from_image(sprite_assets.kitchen.clone()),
-    ));
-
-    // Arena
-    commands.spawn((
-        InGame,
-        Mesh2d(meshes.add(Rectangle::new(GLASS_RADIUS * 2., WATER_LEVEL))),
-        MeshMaterial2d(materials.add(water_color)),
-        Transform::default().with_translation(Vec3::new(0., (WATER_LEVEL - GLASS_HEIGHT) / 2., 2.)),
-    ));
-    commands.spawn((
-        InGame,
-        Mesh2d(meshes.add(Rectangle::new(GLASS_RADIUS * 2., WATER_LEVEL))),
-        MeshMaterial2d(materials.add(water_color)),
-        Transform::default().with_translation(Vec3::new(
-            0.,
-            (WATER_LEVEL - GLASS_HEIGHT) / 2.,
-            -1.,
-        )),
-    ));
-
-    // Glasses BOTTOM
-    commands.spawn((
-        InGame,
-        RigidBody::Static,
-        Collider::rectangle(GLASS_RADIUS * 2., GLASS_WIDTH),
-        Mesh2d(meshes.add(Rectangle::new(GLASS_RADIUS * 2., GLASS_WIDTH))),
-        MeshMaterial2d(materials.add(glass_color)),
-        Transform::default().with_translation(Vec3::new(0., -GLASS_HEIGHT / 2., 0.)),
-        Glass,
-    ));
-
-    // Glasses LEFT
-    commands.spawn((
-        InGame,
-        RigidBody::Static,
-        Collider::rectangle(GLASS_WIDTH, GLASS_HEIGHT),
-        Mesh2d(meshes.add(Rectangle::new(GLASS_WIDTH, GLASS_HEIGHT))),
-        MeshMaterial2d(materials.add(glass_color)),
-        Transform::default().with_translation(Vec3::new(-GLASS_RADIUS, 0., 0.)),
-        Glass,
-    ));
-
-    // Glasses RIGHT
-    commands.spawn((
-        InGame,
-        RigidBody::Static,
-        Collider::rectangle(GLASS_WIDTH, GLASS_HEIGHT),
-        Mesh2d(meshes.add(Rectangle::new(GLASS_WIDTH, GLASS_HEIGHT))),
-        MeshMaterial2d(materials.add(glass_color)),
-        Transform::default().with_translation(Vec3::new(GLASS_RADIUS, 0., 0.)),
-        Glass,
-    ));
 }
 
 fn setup_game_player(
     mut commands: Commands,
-    sprite_assets: Res<SpriteAssets>,
     player_number: Res<PlayerNumber>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<CachetMaterial>>,
+    bot_players: Res<BotPlayers>,
+    bot_settings: Res<BotSettings>,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    player_tablets: Res<PlayerTablets>,
+    teams: Res<Teams>,
+    mut rollback_ids: ResMut<RollbackIds>,
 ) {
-    let width = 1.0 * 128.;
-    let height = 0.2 * 128.;
-    let img = &sprite_assets.cachet;
     for i in 0..player_number.0 {
-        commands.spawn((
+        let tablet_type = player_tablets.get(i);
+        let collider = tablet_type.shape.collider();
+        let tablet = Tablet::new(&tablet_type, &collider, &tuning);
+        let mut player = commands.spawn((
             InGame,
+            rollback_ids.next(),
             RigidBody::Dynamic,
-            Collider::rectangle(width, height),
-            Mesh2d(meshes.add(Rectangle::new(width, height))),
-            MeshMaterial2d(materials.add(CachetMaterial {
-                color: Color::from(PLAYER_COLOR[i]).to_linear(),
-                color_texture: Some(img.clone()),
-            })),
-            Transform::default().with_translation(PLAYER_POSITION[player_number.0 - 1][i]),
-            ColliderDensity(CACHET_DENSITY),
+            Transform::from_translation(arena.spawn_point(player_number.0, i).extend(0.)),
+            ColliderDensity(tablet.density),
             Player(i),
-            Health(INITIAL_HEALTH),
-            Volume(width * height),
-            ExternalForce::default().with_persistence(false),
-        ));
-    }
-}
-
-fn spawn_bubble(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    player: usize,
-    transform: Vec3,
-    direction: Vec3,
-    initial_speed: f32,
-    is_colliding: bool,
-) {
-    let bubble_color =
-        Color::from(PLAYER_COLOR[player]).mix(&Color::from(bevy::color::palettes::css::WHITE), 0.5);
-    if is_colliding {
-        commands.spawn((
-            InGame,
-            Bubble,
-            RigidBody::Dynamic,
-            Collider::circle(BUBBLE_RADIUS),
-            Mesh2d(meshes.add(Circle::new(BUBBLE_RADIUS))),
-            Volume(BUBBLE_RADIUS * BUBBLE_RADIUS * 2. * std::f32::consts::PI),
-            MeshMaterial2d(materials.add(bubble_color)),
-            Transform::from_translation(transform),
-            ColliderDensity(0.1),
-            LinearVelocity(direction.xy() * initial_speed),
-            ExternalForce::default().with_persistence(false),
-        ));
-    } else {
-        commands.spawn((
-            InGame,
-            Bubble,
-            RigidBody::Dynamic,
-            Mesh2d(meshes.add(Circle::new(BUBBLE_RADIUS))),
-            Volume(BUBBLE_RADIUS * BUBBLE_RADIUS * 2. * std::f32::consts::PI),
-            MeshMaterial2d(materials.add(bubble_color)),
-            Transform::from_translation(transform),
-            Mass(BUBBLE_RADIUS * BUBBLE_RADIUS * 2. * std::f32::consts::PI * 0.1),
-            LinearVelocity(direction.xy() * initial_speed),
+            Team(teams.team(i)),
+            PlayerIntent::default(),
+            Health(tablet.max_health),
+            LastAttacker::default(),
+            LastDamage::default(),
+            Volume(tablet.base_volume),
+            collider,
+            tablet,
+            WaterContact::default(),
             ExternalForce::default().with_persistence(false),
         ));
+        if bot_players.is_bot(i) {
+            player.insert(Bot::new(bot_settings.difficulty));
+        }
     }
 }
 
 fn bubble_emiter(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut cachet_query: Query<(&Transform, &Player, &Health), With<Player>>,
+    tuning: Res<GameTuning>,
+    water: Res<Water>,
+    mut game_rng: ResMut<GameRng>,
+    mut pool: ResMut<BubblePool>,
+    mut rollback_ids: ResMut<RollbackIds>,
+    mut cachet_query: Query<(&Transform, &Player, &PlayerIntent, &Health, &Tablet)>,
 ) {
-    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-    rng.gen_range(-60. ..4.);
-    for (transform, player, health) in &mut cachet_query {
-        if is_in_water(&transform.translation) && health.0 > 0. {
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].right)
-                || keyboard_input.pressed(PLAYER_CONTROL[player.0].up)
-            {
-                let is_colliding = rng.gen_bool(0.3);
+    let rng = &mut game_rng.rng;
+    for (transform, player, intent, health, tablet) in &mut cachet_query {
+        // bubbles leave from the edges of the tablet at full size
+        let Vec2 { x: w, y: h } = tablet.half_size;
+        // where the thrusters of each side start, kept inside tablets narrower than 8
+        let inner = 4f32.min(w * 0.5);
+        if water.contains(&transform.translation) && health.0 > 0. {
+            if intent.right > 0. || intent.up > 0. {
+                let is_colliding = rng.random_bool(0.3);
                 let pos = if is_colliding { 0. } else { 1. };
-                for _ in 1..NB_TURBO_PARTICLE {
+                for _ in 1..tuning.nb_turbo_particle {
                     spawn_bubble(
                         &mut commands,
-                        &mut meshes,
-                        &mut materials,
+                        &mut pool,
+                        &mut rollback_ids,
+                        &tuning,
                         player.0,
                         transform.translation
-                            + transform.rotation * Vec3::new(rng.gen_range(-60. ..4.), -13., pos),
-                        (transform.rotation * Vec3::NEG_Y),
-                        BUBBLE_EMMISSION_SPEED * (1. - pos),
+                            + transform.rotation * Vec3::new(rng.random_range(-w..inner), -h, pos),
+                        transform.rotation * Vec3::NEG_Y,
+                        tuning.bubble_emmission_speed * (1. - pos),
                         is_colliding,
                     );
                 }
             }
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].left)
-                || keyboard_input.pressed(PLAYER_CONTROL[player.0].up)
-            {
-                let is_colliding = rng.gen_bool(0.3);
+            if intent.left > 0. || intent.up > 0. {
+                let is_colliding = rng.random_bool(0.3);
                 let pos = if is_colliding { 0. } else { 1. };
-                for _ in 1..NB_TURBO_PARTICLE {
+                for _ in 1..tuning.nb_turbo_particle {
                     spawn_bubble(
                         &mut commands,
-                        &mut meshes,
-                        &mut materials,
+                        &mut pool,
+                        &mut rollback_ids,
+                        &tuning,
                         player.0,
                         transform.translation
-                            + transform.rotation * Vec3::new(rng.gen_range(4. ..60.), -13., pos),
-                        (transform.rotation * Vec3::NEG_Y),
-                        BUBBLE_EMMISSION_SPEED * (1. - pos),
+                            + transform.rotation * Vec3::new(rng.random_range(inner..w), -h, pos),
+                        transform.rotation * Vec3::NEG_Y,
+                        tuning.bubble_emmission_speed * (1. - pos),
                         is_colliding,
                     );
                 }
             }
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].down) {
-                let is_colliding = rng.gen_bool(0.7);
+            if intent.down > 0. {
+                let is_colliding = rng.random_bool(0.7);
                 let pos = if is_colliding { 0. } else { 1. };
-                for _ in 1..NB_TURBO_PARTICLE * 2 {
+                for _ in 1..tuning.nb_turbo_particle * 2 {
                     spawn_bubble(
                         &mut commands,
-                        &mut meshes,
-                        &mut materials,
+                        &mut pool,
+                        &mut rollback_ids,
+                        &tuning,
                         player.0,
                         transform.translation
-                            + transform.rotation * Vec3::new(rng.gen_range(-60. ..60.), 13., pos),
-                        (transform.rotation * Vec3::NEG_Y),
-                        BUBBLE_EMMISSION_SPEED * (1. - pos),
+                            + transform.rotation * Vec3::new(rng.random_range(-w..w), h, pos),
+                        transform.rotation * Vec3::NEG_Y,
+                        tuning.bubble_emmission_speed * (1. - pos),
                         is_colliding,
                     );
                 }
             }
             spawn_bubble(
                 &mut commands,
-                &mut meshes,
-                &mut materials,
+                &mut pool,
+                &mut rollback_ids,
+                &tuning,
                 player.0,
                 transform.translation
                     + transform.rotation
-                        * Vec3::new(rng.gen_range(-60. ..60.), rng.gen_range(-12. ..12.), 1.),
-                (transform.rotation * Vec3::NEG_Y),
+                        * Vec3::new(rng.random_range(-w..w), rng.random_range(-h..h), 1.),
+                transform.rotation * Vec3::NEG_Y,
                 0.,
                 false,
             );
@@ -320,187 +457,223 @@ fn bubble_emiter(
     }
 }
 
+// buoyancy, drag and extra gravity are blended by the share of the body under the surface
 fn drag_force(
+    tuning: Res<GameTuning>,
+    water: Res<Water>,
     mut in_water_object: Query<(
         &Transform,
         &Volume,
+        Option<&Collider>,
         &mut LinearVelocity,
         &mut AngularVelocity,
         &mut ExternalForce,
         &ComputedMass,
     )>,
 ) {
-    for (transform, volume, mut linear_velocity, mut angular_velocity, mut force, mass) in
+    for (transform, volume, collider, mut linear_velocity, mut angular_velocity, mut force, mass) in
         &mut in_water_object
     {
-        if is_in_water(&transform.translation) {
-            let archimede = FLUID_DENSITY * GRAVITY * volume.0 * Vec2::Y;
-            linear_velocity.0 = (1. - DRAG_WATER_COEFFICIENT) * linear_velocity.0;
-            angular_velocity.0 = (1. - DRAG_WATER_COEFFICIENT) * angular_velocity.0;
-            force.apply_force(archimede);
-        } else {
-            let double_gravity = GRAVITY * GRAVITY_SCALE * 3.0 * mass.value() * Vec2::NEG_Y;
-            linear_velocity.0 = (1. - DRAG_AIR_COEFFICIENT) * linear_velocity.0;
-            angular_velocity.0 = (1. - DRAG_AIR_COEFFICIENT) * angular_velocity.0;
-            force.apply_force(double_gravity);
+        let (submerged, center_of_buoyancy) = water.submerged(collider, transform);
+
+        let archimede = tuning.fluid_density * tuning.gravity * volume.0 * submerged * Vec2::Y;
+        force.apply_force_at_point(archimede, center_of_buoyancy, Vec2::ZERO);
+
+        let drag = tuning.drag_air_coefficient
+            + (tuning.drag_water_coefficient - tuning.drag_air_coefficient) * submerged;
+        linear_velocity.0 = (1. - drag) * linear_velocity.0;
+        angular_velocity.0 *= 1. - drag;
+
+        let double_gravity = tuning.gravity
+            * tuning.gravity_scale
+            * 3.0
+            * mass.value()
+            * (1. - submerged)
+            * Vec2::NEG_Y;
+        force.apply_force(double_gravity);
+
+        // floating bodies slide down the slope of the waves
+        let x = transform.translation.x;
+        if submerged > 0. && water.surface_y(x) - transform.translation.y < volume.0.sqrt() {
+            force.apply_force(-water.slope(x) * tuning.wave_push * volume.0 * submerged * Vec2::X);
         }
     }
 }
 
-pub fn is_in_water(translation: &Vec3) -> bool {
-    translation.y <= (WATER_LEVEL * 0.5 - (GLASS_HEIGHT - WATER_LEVEL) / 2.) - 10.
-        && translation.y >= GLASS_HEIGHT * -0.5
-        && translation.x >= -GLASS_RADIUS
-        && translation.x <= GLASS_RADIUS
-}
-
 fn use_turbo(
-    commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut cachet_query: Query<(&Transform, &Player, &mut ExternalForce, &mut Health), With<Player>>,
+    tuning: Res<GameTuning>,
+    water: Res<Water>,
+    mut cachet_query: Query<
+        (
+            &Transform,
+            &PlayerIntent,
+            &Tablet,
+            Option<&TurboBoost>,
+            &mut ExternalForce,
+            &mut Health,
+            &mut LastDamage,
+        ),
+        With<Player>,
+    >,
 ) {
-    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-    rng.gen_range(-60. ..4.);
-    let amplitude = Vec3::Y * TURBO_FORCE;
-    let left_bottom = Vec3::new(-32., -13., 0.);
-    let right_bottom = Vec3::new(32., -13., 0.);
-    let top = Vec3::new(0., 13., 0.);
     let center = Vec3::new(0., 0., 0.);
-    for (transform, player, mut force, mut health) in &mut cachet_query {
-        if is_in_water(&transform.translation) && health.0 > 0. {
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].up) {
+    // thrusters sit on the edges of the tablet, wherever it has dissolved to
+    for (transform, intent, tablet, boost, mut force, mut health, mut last_damage) in
+        &mut cachet_query
+    {
+        let turbo_damage = tuning.turbo_tick_damage * tuning.global_damage_scale;
+        let left_bottom = Vec3::new(-tablet.half_size.x / 2., -tablet.half_size.y, 0.);
+        let right_bottom = Vec3::new(tablet.half_size.x / 2., -tablet.half_size.y, 0.);
+        let top = Vec3::new(0., tablet.half_size.y, 0.);
+        let boost = boost.map_or(1., |_| tuning.lemon_turbo_scale);
+        let amplitude = Vec3::Y * tuning.turbo_force * tablet.thrust_scale * boost;
+        if water.contains(&transform.translation) && health.0 > 0. {
+            if intent.up > 0. {
                 force.apply_force_at_point(
-                    (transform.rotation * amplitude).xy(),
+                    (transform.rotation * amplitude * intent.up).xy(),
                     (transform.rotation * center).xy(),
                     (transform.rotation * center).xy(),
                 );
-                health.0 -= TURBO_TICK_DAMAGE * GLOBAL_DAMAGE_SCALE;
+                health.damage(
+                    turbo_damage * intent.up,
+                    DamageSource::Turbo,
+                    &mut last_damage,
+                );
             }
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].right) {
+            if intent.right > 0. {
                 force.apply_force_at_point(
-                    (transform.rotation * amplitude).xy(),
-                    (transform.rotation * left_bottom).xy(),
+                    (transform.rotation * amplitude * intent.right).xy(),
+                    (transform.rotation * (left_bottom * transform.scale)).xy(),
                     (transform.rotation * center).xy(),
                 );
-                health.0 -= TURBO_TICK_DAMAGE * GLOBAL_DAMAGE_SCALE;
+                health.damage(
+                    turbo_damage * intent.right,
+                    DamageSource::Turbo,
+                    &mut last_damage,
+                );
             }
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].left) {
+            if intent.left > 0. {
                 force.apply_force_at_point(
-                    (transform.rotation * amplitude).xy(),
-                    (transform.rotation * right_bottom).xy(),
+                    (transform.rotation * amplitude * intent.left).xy(),
+                    (transform.rotation * (right_bottom * transform.scale)).xy(),
                     (transform.rotation * center).xy(),
                 );
-                health.0 -= TURBO_TICK_DAMAGE * GLOBAL_DAMAGE_SCALE;
+                health.damage(
+                    turbo_damage * intent.left,
+                    DamageSource::Turbo,
+                    &mut last_damage,
+                );
             }
-            if keyboard_input.pressed(PLAYER_CONTROL[player.0].down) {
+            if intent.down > 0. {
                 force.apply_force_at_point(
-                    (transform.rotation * -amplitude).xy(),
-                    (transform.rotation * top).xy(),
+                    (transform.rotation * -amplitude * intent.down).xy(),
+                    (transform.rotation * (top * transform.scale)).xy(),
                     (transform.rotation * center).xy(),
                 );
-                health.0 -= TURBO_TICK_DAMAGE * GLOBAL_DAMAGE_SCALE;
+                health.damage(
+                    turbo_damage * intent.down,
+                    DamageSource::Turbo,
+                    &mut last_damage,
+                );
             }
         }
     }
 }
 
-fn update_camera(
-    mut camera_query: Query<
-        (&mut Transform, &mut OrthographicProjection),
-        (With<Camera2d>, Without<Player>),
-    >,
-    player_query: Query<(&Transform, &Health), (With<Player>, Without<Camera2d>)>,
+fn update_health(
+    tuning: Res<GameTuning>,
+    water: Res<Water>,
+    mut query: Query<(&mut Health, &mut LastDamage, &Transform)>,
 ) {
-    // arena center participation
-    let mut interest_area = Rect::new(
-        -0.5 * GLASS_RADIUS * 2.0,
-        -0.5 * GLASS_HEIGHT,
-        0.5 * GLASS_RADIUS * 2.0,
-        0.5 * GLASS_HEIGHT,
-    );
-
-    // players participation
-    for (player_transform, health) in player_query.iter() {
-        if health.0 > 0. {
-            interest_area = interest_area.union_point(player_transform.translation.xy());
-        }
-    }
-
-    // buffer
-    interest_area = interest_area.inflate(interest_area.size().max_element() * CAM_BUFFER);
-    let center = interest_area.center();
-    let target_position = Vec3::new(center.x, center.y, 0.);
-
-    for (mut camera_transform, mut cam) in &mut camera_query {
-        let new_camera_translate = CAM_ELASTICITY * camera_transform.translation
-            + (1.0 - CAM_ELASTICITY) * target_position;
-        camera_transform.translation = new_camera_translate;
-
-        let mut cam_area = cam.area;
-        cam_area.min += new_camera_translate.xy();
-        cam_area.max += new_camera_translate.xy();
-
-        let mut zoom: f32 = cam.scale;
-
-        if cam_area.union(interest_area) != cam_area {
-            zoom *= 1. + CAM_ZOOM_SPEED;
-        }
-
-        let inner = cam_area.inflate(-200.);
-        if inner.union(interest_area) == inner {
-            zoom *= 1. - CAM_ZOOM_SPEED;
+    for (mut health, mut last_damage, transform) in &mut query {
+        if water.contains(&transform.translation) {
+            health.damage(
+                tuning.global_damage_scale * tuning.water_tick_damage,
+                DamageSource::Water,
+                &mut last_damage,
+            );
         }
-
-        zoom = zoom.clamp(CAM_ZOOM_MIN, CAM_ZOOM_MAX);
-        cam.scale = zoom;
     }
 }
 
-fn update_health(mut query: Query<(&mut Health, &Transform)>) {
-    for (mut health, transform) in &mut query {
-        if is_in_water(&transform.translation) {
-            health.0 -= GLOBAL_DAMAGE_SCALE * WATER_TICK_DAMAGE;
+// a fizzing tablet gets smaller, lighter and more buoyant as it loses health
+fn dissolve_tablets(
+    tuning: Res<GameTuning>,
+    mut query: Query<
+        (
+            &Health,
+            &Tablet,
+            &mut Transform,
+            &mut Volume,
+            &mut ColliderDensity,
+        ),
+        With<Player>,
+    >,
+) {
+    for (health, tablet, mut transform, mut volume, mut density) in &mut query {
+        if health.0 <= 0. {
+            continue;
         }
-    }
-}
+        let remaining = (health.0 / tablet.max_health).clamp(0., 1.);
+        let scale = tuning.min_tablet_scale + (1. - tuning.min_tablet_scale) * remaining;
+        let new_density =
+            tuning.dissolved_density + (tablet.density - tuning.dissolved_density) * remaining;
 
-fn try_kill_bubbles(mut commands: Commands, query: Query<(Entity, &Transform), With<Bubble>>) {
-    for (entity, transform) in query.iter() {
-        if !is_in_water(&transform.translation) {
-            commands.entity(entity).despawn();
+        if transform.scale.x != scale {
+            transform.scale = Vec3::new(scale, scale, 1.);
+            volume.0 = tablet.base_volume * scale * scale;
+        }
+        if density.0 != new_density {
+            density.0 = new_density;
         }
     }
 }
 
 fn try_kill_by_health(
     mut commands: Commands,
+    mut eliminated: EventWriter<PlayerEliminated>,
     mut query: Query<(
         Entity,
         &Health,
         &Player,
+        &LastAttacker,
+        &LastDamage,
+        &Tablet,
+        Has<Dissolved>,
         &mut ColliderDensity,
         &mut Transform,
         &mut LinearVelocity,
     )>,
 ) {
-    for (entity, health, player, mut density, mut transform, mut vel) in query.iter_mut() {
+    for (
+        entity,
+        health,
+        player,
+        last_attacker,
+        last_damage,
+        tablet,
+        dissolved,
+        mut density,
+        mut transform,
+        mut vel,
+    ) in query.iter_mut()
+    {
         if health.0 <= 0. {
-            if transform.scale.x == 1. {
-                // bye bye message
-                let mut rng = rand::rng();
-                let choice = rng.random_range(0..3);
-                match choice {
-                    0 => warn!("Player {:?} disolved :'(", player.0),
-                    1 => warn!("Player {:?} didn't want to fight anymore", player.0),
-                    _ => warn!("Player {:?} left the battle arena", player.0),
-                }
+            if !dissolved {
+                commands.entity(entity).insert(Dissolved);
+                eliminated.send(PlayerEliminated {
+                    player: player.0,
+                    cause: match last_damage.0 {
+                        Some(DamageSource::Turbo) => EliminationCause::SelfFizz,
+                        _ => EliminationCause::Dissolved,
+                    },
+                    attacker: last_attacker.0,
+                });
             }
 
             transform.scale -= 0.005;
             vel.0 *= 0.95;
-            density.0 = CACHET_DENSITY / 3.;
+            density.0 = tablet.density / 3.;
 
             if transform.scale.x < 0.05 {
                 commands.entity(entity).despawn();
@@ -509,44 +682,231 @@ fn try_kill_by_health(
     }
 }
 
+// decide the round once at most one team is still fizzing
 fn end_game_condition(
+    mut commands: Commands,
     player_number: Res<PlayerNumber>,
-    mut app_state: ResMut<NextState<AppState>>,
-    mut menu_state: ResMut<NextState<MainMenuState>>,
-    query: Query<&Health, With<Player>>,
-    query_end_menu: Query<(), With<EndGameDisplay>>,
+    round_outcome: Option<Res<RoundOutcome>>,
+    mut match_score: ResMut<MatchScore>,
+    query: Query<(&Health, &Team)>,
 ) {
-    if !query_end_menu.is_empty() {
+    if round_outcome.is_some() {
         return;
     }
 
-    let mut alive_players = 0;
-    for health in query.iter() {
-        if health.0 > 0. {
-            alive_players += 1;
+    let mut alive_teams = Vec::new();
+    for (health, team) in query.iter() {
+        if health.0 > 0. && !alive_teams.contains(&team.0) {
+            alive_teams.push(team.0);
+        }
+    }
+
+    if (player_number.0 == 1 && alive_teams.is_empty())
+        || (player_number.0 != 1 && alive_teams.len() <= 1)
+    {
+        let winner = alive_teams.first().copied();
+        if let Some(winner) = winner {
+            match_score.add_win(winner);
         }
+        commands.insert_resource(RoundOutcome { winner });
     }
 }
 
 // kill the player when they are out of the playable area
-fn try_kill_by_zone(mut query: Query<(&mut Health, &Transform), With<Player>>) {
-    for (mut health, transform) in query.iter_mut() {
-        if transform.translation.y < GLASS_HEIGHT * -0.5 {
-            health.0 = 0.;
-            if transform.scale.x == 1. {
-                warn!("player left the area like a wuss");
+fn try_kill_by_zone(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut eliminated: EventWriter<PlayerEliminated>,
+    mut query: Query<(Entity, &Player, &LastAttacker, &Transform, &mut Health)>,
+) {
+    for (entity, player, last_attacker, transform, mut health) in query.iter_mut() {
+        if transform.translation.y < arena.bounds.min.y {
+            if health.0 > 0. {
+                commands.entity(entity).insert(Dissolved);
+                eliminated.send(PlayerEliminated {
+                    player: player.0,
+                    cause: EliminationCause::KnockedOut,
+                    attacker: last_attacker.0,
+                });
             }
+            health.0 = 0.;
         }
     }
 }
 
+fn seed_round(mut game_rng: ResMut<GameRng>, round_seed: Option<Res<RoundSeed>>) {
+    let seed = round_seed.map_or_else(rand::random, |round_seed| round_seed.0);
+    *game_rng = GameRng::from_seed(seed);
+}
+
+fn pause_physics(mut time: ResMut<Time<Physics>>) {
+    time.pause();
+}
+
+fn resume_physics(mut time: ResMut<Time<Physics>>) {
+    time.unpause();
+}
+
+fn restart_round(mut app_state: ResMut<NextState<AppState>>) {
+    app_state.set(AppState::InGame);
+}
+
 fn on_game_exit(mut commands: Commands, query: Query<Entity, With<InGame>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
+    commands.remove_resource::<RoundOutcome>();
+    commands.insert_resource(RollbackIds::default());
+}
+
+/// Fixed step systems that play the match, turned off on a `ThinClient` which mirrors the server instead.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationSet;
+
+/// Simulation of a match: physics, tablets, damage and win condition.
+/// Holds no window, rendering, audio or menu so it can also run headless.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>();
+        app.add_sub_state::<PauseState>();
+
+        app.add_plugins(PhysicsPlugins::default());
+        app.configure_sets(
+            FixedPreUpdate,
+            SimulationSet.run_if(not(resource_exists::<ThinClient>)),
+        );
+        app.configure_sets(
+            FixedUpdate,
+            SimulationSet.run_if(not(resource_exists::<ThinClient>)),
+        );
+        app.configure_sets(
+            FixedPostUpdate,
+            SimulationSet.run_if(not(resource_exists::<ThinClient>)),
+        );
+        app.add_plugins(GameTuningPlugin);
+        app.add_plugins(ArenaPlugin);
+        app.add_plugins(OnHitPlugin);
+        app.add_plugins(BotPlugin);
+        app.add_plugins(WaterPlugin);
+        app.add_plugins(BubblesPlugin);
+        app.add_plugins(PickupPlugin);
+        app.add_plugins(HazardPlugin);
+        app.add_plugins(TabletPlugin);
+        app.add_plugins(RollbackPlugin);
+
+        app.init_resource::<MatchScore>();
+        app.init_resource::<Teams>();
+
+        app.init_resource::<GameRng>();
+        app.add_event::<PlayerEliminated>();
+
+        app.rollback_resource::<GameRng>()
+            .rollback_resource::<MatchScore>()
+            .rollback_resource::<RoundOutcome>()
+            .rollback_component::<InGame>()
+            .rollback_component::<Player>()
+            .rollback_component::<Team>()
+            .rollback_component::<PlayerIntent>()
+            .rollback_component::<Health>()
+            .rollback_component::<LastAttacker>()
+            .rollback_component::<LastDamage>()
+            .rollback_component::<Volume>()
+            .rollback_component::<Glass>()
+            .rollback_component::<Dissolved>();
+
+        // chained so that every simulated entity gets the same `Rollback` id from one run to the next
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (seed_round, setup_game_player, setup_glasses).chain(),
+        );
+        app.add_systems(OnEnter(AppState::RestartRound), restart_round);
+
+        // chained so that a match replays identically from the same seed and inputs
+        app.add_systems(
+            FixedUpdate,
+            (
+                use_turbo,
+                bubble_emiter,
+                drag_force,
+                update_health,
+                dissolve_tablets,
+            )
+                .chain()
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+
+        app.add_systems(
+            FixedPostUpdate,
+            (
+                try_kill_bubbles,
+                try_kill_by_health,
+                try_kill_by_zone,
+                end_game_condition,
+            )
+                .chain()
+                .after(PhysicsSet::Sync)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+        app.add_systems(OnExit(AppState::InGame), on_game_exit);
+        app.add_systems(OnEnter(PauseState::Running), resume_physics);
+        app.add_systems(OnExit(PauseState::Running), pause_physics);
+    }
+}
+
+/// Builds an app running only the simulation, without window, rendering or audio.
+/// Once run it updates at the pace of the fixed timestep.
+pub fn simulation_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+            Time::<Fixed>::default().timestep(),
+        )),
+        StatesPlugin,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        bevy::scene::ScenePlugin,
+    ));
+    app.init_resource::<Assets<Mesh>>();
+    app.add_plugins(GamePlugin);
+    app
+}
+
+/// Builds a `simulation_app` where every `app.update()` advances exactly one fixed tick,
+/// and the match starts right away.
+pub fn headless_app(player_number: usize) -> App {
+    let mut app = simulation_app();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ));
+    app.insert_resource(PlayerNumber(player_number));
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::InGame);
+    app
 }
 
 pub fn run() {
+    let settings = GameSettings::load();
+    let (width, height) = settings.resolution;
+    let args: Vec<String> = std::env::args().collect();
+    let net_config = NetConfig::from_args(&args);
+    if net_config.is_none() && args.iter().any(|arg| arg == "--lan") {
+        eprintln!("usage: --lan <player index> <address of every player>...");
+    }
+    let server = server_address(&args, "--connect");
+    if server.is_none() && args.iter().any(|arg| arg == "--connect") {
+        eprintln!("usage: --connect <address of a tablet-server>");
+    }
+    let spectated = server_address(&args, "--spectate");
+    if spectated.is_none() && args.iter().any(|arg| arg == "--spectate") {
+        eprintln!("usage: --spectate <address of a tablet-server>");
+    }
+
     let mut app = App::new();
     app.add_plugins(
         DefaultPlugins
@@ -555,9 +915,10 @@ pub fn run() {
                     title: "Tablet Takedown".to_string(),
                     canvas: Some("#my-bevy".into()),
                     fit_canvas_to_parent: true,
-                    resolution: WindowResolution::new(1920., 1080.),
+                    resolution: WindowResolution::new(width as f32, height as f32),
+                    mode: settings.display_mode.window_mode(),
                     prevent_default_event_handling: true,
-                    present_mode: PresentMode::AutoVsync,
+                    present_mode: settings.present_mode(),
                     ..default()
                 }),
                 ..default()
@@ -569,50 +930,67 @@ pub fn run() {
             }),
     );
     app.add_plugins(Material2dPlugin::<CachetMaterial>::default());
+    app.insert_resource(settings);
+    if let Some(net_config) = net_config {
+        app.insert_resource(net_config);
+    }
+    let client = match (server, spectated) {
+        (Some(server), _) => Some((server, ThinClient::connect(server))),
+        (None, Some(server)) => Some((server, ThinClient::spectate(server))),
+        (None, None) => None,
+    };
+    if let Some((server, client)) = client {
+        match client {
+            Ok(client) => {
+                app.insert_resource(client);
+            }
+            Err(err) => eprintln!("Could not reach {server}: {err}"),
+        }
+    }
 
-    app.init_state::<AppState>().add_loading_state(
+    app.add_plugins(GamePlugin);
+
+    app.add_loading_state(
         LoadingState::new(AppState::AssetLoading)
             .continue_to_state(AppState::MainMenu)
             .load_collection::<AudioAssets>()
             .load_collection::<FontAssets>()
-            .load_collection::<SpriteAssets>(),
+            .load_collection::<SpriteAssets>()
+            .load_collection::<TuningAssets>()
+            .load_collection::<ArenaAssets>()
+            .load_collection::<TabletAssets>(),
     );
     app.init_state::<MainMenuState>();
-    app.insert_resource(Gravity(Vec2::NEG_Y * GRAVITY * GRAVITY_SCALE));
-
-    app.add_plugins(PhysicsPlugins::default());
 
     app.add_plugins(MainMenuPlugin);
+    app.add_plugins(LobbyPlugin);
+    app.add_plugins(PauseMenuPlugin);
     app.add_plugins(AudioPlugin);
     app.add_plugins(MyAudioPlugin);
     app.add_plugins(GameHudPlugin);
-    app.add_plugins(OnHitPlugin);
-
-    app.add_systems(Startup, setup);
+    app.add_plugins(GameRenderPlugin);
+    app.add_plugins(SpectatorPlugin);
+    app.add_plugins(PictureInPicturePlugin);
+    app.add_plugins(PlayerInputPlugin);
+    app.add_plugins(GameMatchPlugin);
+    app.add_plugins(ReplayPlugin);
+    app.add_plugins(NetcodePlugin);
+    app.add_plugins(ThinClientPlugin);
+    app.add_plugins(SettingsPlugin);
 
-    app.add_systems(OnEnter(AppState::InGame), resetup);
-    app.add_systems(OnEnter(AppState::InGame), setup_game_player);
-    app.add_systems(OnEnter(AppState::InGame), setup_glasses);
-
-    app.add_systems(Update, update_camera.run_if(in_state(AppState::InGame)));
-
-    app.add_systems(
-        FixedUpdate,
-        (use_turbo, bubble_emiter, drag_force, update_health).run_if(in_state(AppState::InGame)),
-    );
+    app.run();
+}
 
-    app.add_systems(
-        FixedPostUpdate,
-        (
-            try_kill_bubbles,
-            try_kill_by_health,
-            try_kill_by_zone,
-            end_game_condition,
-        )
-            .run_if(in_state(AppState::InGame)),
-    );
-    app.add_systems(OnExit(AppState::InGame), on_game_exit);
+/// Entry point of the `tablet-server` binary: plays matches headless for the thin clients that join.
+pub fn run_server() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(config) = ServerConfig::from_args(&args) else {
+        eprintln!("usage: tablet-server [address to listen on] [number of players] [best of]");
+        return;
+    };
 
+    let mut app = server_app(config);
+    app.add_plugins(LogPlugin::default());
     app.run();
 }
 
@@ -632,8 +1010,8 @@ pub struct SpriteAssets {
     pub cup: Handle<Image>,
     #[asset(path = "audio/help.png")]
     pub help: Handle<Image>,
-    #[asset(path = "audio/Kitchen.png")]
-    pub kitchen: Handle<Image>,
+    #[asset(path = "audio/mini_bubble.png")]
+    pub mini_bubble: Handle<Image>,
     #[asset(path = "audio/p1_won.png")]
     pub p1_won: Handle<Image>,
     #[asset(path = "audio/p2_won.png")]
@@ -0,0 +1,544 @@
+use bevy::{
+    app::{Plugin, Update},
+    asset::Assets,
+    color::{Color, Srgba},
+    input::{
+        gamepad::{Gamepad, GamepadButton},
+        ButtonInput,
+    },
+    prelude::{
+        in_state, BuildChildren, Button, Changed, ChildBuild, ChildBuilder, Commands, Component,
+        Condition, DespawnRecursiveExt, DetectChanges, Entity, IntoSystemConfigs, KeyCode,
+        NextState, OnExit, Query, Res, ResMut, Resource, Text, With,
+    },
+    text::{TextColor, TextFont},
+    ui::{AlignItems, BackgroundColor, FlexDirection, Interaction, Node, Val},
+    utils::default,
+};
+
+use crate::{
+    arena::arena_plugin::{Arena, ArenaAssets, ArenaChoice},
+    bot::bot_plugin::BotPlayers,
+    game_match::game_match_plugin::{MatchScore, MatchSettings},
+    hazard::hazard_plugin::{toggle_label, HazardSettings},
+    main_menu::{
+        main_menu_plugin::{create_button, MenuCanvas},
+        TEXT_COLOR,
+    },
+    player_input::player_input_plugin::{
+        key_label, InputDevice, PlayerControls, PlayerDevices, Thruster,
+    },
+    tablet::tablet_plugin::{PlayerTablets, TabletAssets, TabletType},
+    AppState, FontAssets, MainMenuState, PlayerNumber, Teams, MAX_PLAYERS, PLAYER_PALETTE,
+    TEAM_COLOR,
+};
+
+/// Colour picked in the lobby by each `Player` index.
+#[derive(Resource, Clone)]
+pub struct PlayerColors(pub Vec<Srgba>);
+
+impl Default for PlayerColors {
+    fn default() -> Self {
+        PlayerColors(PLAYER_PALETTE[..MAX_PLAYERS].to_vec())
+    }
+}
+
+impl PlayerColors {
+    pub fn get(&self, player: usize) -> Color {
+        let color = self
+            .0
+            .get(player)
+            .copied()
+            .unwrap_or(PLAYER_PALETTE[player % PLAYER_PALETTE.len()]);
+        Color::from(color)
+    }
+
+    /// Colour of a team, or of the player outside of team matches.
+    pub fn side(&self, teams: &Teams, side: usize) -> Color {
+        if teams.is_team_match() {
+            Color::from(TEAM_COLOR[side % TEAM_COLOR.len()])
+        } else {
+            self.get(side)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LobbySlot {
+    // kept when a human hands their slot to a bot, so they can take it back
+    device: Option<InputDevice>,
+    color: usize,
+    tablet: usize,
+    team: usize,
+    bot: bool,
+    ready: bool,
+}
+
+/// Players who joined the next match, in `Player` index order.
+#[derive(Resource, Default)]
+pub struct Lobby {
+    slots: Vec<LobbySlot>,
+    team_match: bool,
+    friendly_fire: bool,
+}
+
+impl Lobby {
+    pub fn with_bots(bots: usize) -> Self {
+        let mut lobby = Lobby::default();
+        for _ in 0..bots {
+            lobby.join(None);
+        }
+        lobby
+    }
+
+    fn slot_of(&self, device: InputDevice) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.device == Some(device))
+    }
+
+    fn color_taken(&self, color: usize) -> bool {
+        self.slots.iter().any(|slot| slot.color == color)
+    }
+
+    // a device-less slot is a bot
+    fn join(&mut self, device: Option<InputDevice>) {
+        if self.slots.len() >= MAX_PLAYERS {
+            return;
+        }
+        let color = (0..PLAYER_PALETTE.len())
+            .find(|color| !self.color_taken(*color))
+            .unwrap_or_default();
+        // newcomers even out the teams
+        let team = (0..TEAM_COLOR.len())
+            .min_by_key(|team| self.slots.iter().filter(|slot| slot.team == *team).count())
+            .unwrap_or_default();
+        self.slots.push(LobbySlot {
+            device,
+            color,
+            tablet: 0,
+            team,
+            bot: device.is_none(),
+            ready: device.is_none(),
+        });
+    }
+
+    // skips the colours of the other slots, so every player keeps a unique one
+    fn cycle_color(&mut self, slot: usize, forward: bool) {
+        let count = PLAYER_PALETTE.len();
+        let step = if forward { 1 } else { count - 1 };
+        let mut color = self.slots[slot].color;
+        for _ in 0..count {
+            color = (color + step) % count;
+            if !self.color_taken(color) {
+                self.slots[slot].color = color;
+                return;
+            }
+        }
+    }
+
+    fn cycle_tablet(&mut self, slot: usize, type_count: usize) {
+        let tablet = &mut self.slots[slot].tablet;
+        *tablet = (*tablet + 1) % type_count.max(1);
+    }
+
+    fn toggle_bot(&mut self, slot: usize) {
+        let slot = &mut self.slots[slot];
+        if slot.device.is_some() {
+            slot.bot = !slot.bot;
+            slot.ready = slot.bot;
+        }
+    }
+
+    fn cycle_team(&mut self, slot: usize) {
+        let team = &mut self.slots[slot].team;
+        *team = (*team + 1) % TEAM_COLOR.len();
+    }
+
+    /// At least one human joined and every one of them is ready,
+    /// and in a team match both teams have someone.
+    fn is_ready(&self) -> bool {
+        let teams_filled = !self.team_match
+            || (0..TEAM_COLOR.len()).all(|team| self.slots.iter().any(|slot| slot.team == team));
+        teams_filled
+            && self.slots.iter().any(|slot| !slot.bot)
+            && self.slots.iter().all(|slot| slot.ready)
+    }
+
+    fn teams(&self) -> Teams {
+        Teams {
+            teams: if self.team_match {
+                self.slots.iter().map(|slot| slot.team).collect()
+            } else {
+                Vec::new()
+            },
+            friendly_fire: self.friendly_fire,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum LobbyAction {
+    Ready,
+    PreviousColor,
+    NextColor,
+    NextTablet,
+}
+
+impl From<Thruster> for LobbyAction {
+    fn from(thruster: Thruster) -> Self {
+        match thruster {
+            Thruster::Up => LobbyAction::Ready,
+            Thruster::Left => LobbyAction::PreviousColor,
+            Thruster::Right => LobbyAction::NextColor,
+            Thruster::Down => LobbyAction::NextTablet,
+        }
+    }
+}
+
+const GAMEPAD_ACTIONS: [(GamepadButton, LobbyAction); 5] = [
+    (GamepadButton::South, LobbyAction::Ready),
+    (GamepadButton::DPadLeft, LobbyAction::PreviousColor),
+    (GamepadButton::DPadRight, LobbyAction::NextColor),
+    (GamepadButton::DPadDown, LobbyAction::NextTablet),
+    (GamepadButton::West, LobbyAction::NextTablet),
+];
+
+#[derive(Component)]
+enum LobbyButton {
+    Color(usize),
+    Tablet(usize),
+    Bot(usize),
+    Team(usize),
+    Leave(usize),
+    TeamMatch,
+    FriendlyFire,
+    AddBot,
+    Back,
+}
+
+/// Holds every node of the lobby.
+#[derive(Component)]
+struct LobbyLayout;
+
+/// Player cards and match options, rebuilt whenever the lobby changes.
+#[derive(Component)]
+struct LobbySlotsPanel;
+
+/// Last stop before a match: players join with their controls, then pick a colour,
+/// a tablet and whether a bot plays for them.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<Lobby>();
+        app.init_resource::<PlayerColors>();
+        app.add_systems(OnExit(MainMenuState::Lobby), despawn_lobby);
+        app.add_systems(
+            Update,
+            (
+                join_lobby,
+                button_on_press_lobby_system,
+                refresh_lobby,
+                start_when_ready,
+            )
+                .chain()
+                .run_if(in_state(AppState::MainMenu).and(in_state(MainMenuState::Lobby))),
+        );
+    }
+}
+
+fn slot_label(player: usize, slot: &LobbySlot, controls: &PlayerControls) -> String {
+    let controls = match slot.device {
+        _ if slot.bot => "Bot".to_string(),
+        Some(InputDevice::Keyboard(key_map)) => Thruster::ALL
+            .map(|thruster| key_label(controls.0[key_map].key(thruster)))
+            .join(""),
+        Some(InputDevice::Gamepad(_)) => "Pad".to_string(),
+        None => "Bot".to_string(),
+    };
+    format!("P{} {}", player + 1, controls)
+}
+
+// keys and buttons of a device join the lobby, then drive its slot
+fn join_lobby(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls: Res<PlayerControls>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    tablet_assets: Res<TabletAssets>,
+    mut lobby: ResMut<Lobby>,
+) {
+    let mut presses = Vec::new();
+    for (key_map, keys) in controls.0.iter().enumerate() {
+        for thruster in Thruster::ALL {
+            if keyboard_input.just_pressed(keys.key(thruster)) {
+                presses.push((InputDevice::Keyboard(key_map), Some(thruster.into())));
+            }
+        }
+    }
+    for (entity, gamepad) in gamepads.iter() {
+        if gamepad.get_just_pressed().next().is_some() {
+            let action = GAMEPAD_ACTIONS
+                .iter()
+                .find(|(button, _)| gamepad.just_pressed(*button))
+                .map(|(_, action)| *action);
+            presses.push((InputDevice::Gamepad(entity), action));
+        }
+    }
+
+    for (device, action) in presses {
+        let Some(slot) = lobby.slot_of(device) else {
+            lobby.join(Some(device));
+            continue;
+        };
+        let (Some(action), false) = (action, lobby.slots[slot].bot) else {
+            continue;
+        };
+        match action {
+            LobbyAction::Ready => lobby.slots[slot].ready = !lobby.slots[slot].ready,
+            _ if lobby.slots[slot].ready => (),
+            LobbyAction::PreviousColor => lobby.cycle_color(slot, false),
+            LobbyAction::NextColor => lobby.cycle_color(slot, true),
+            LobbyAction::NextTablet => lobby.cycle_tablet(slot, tablet_assets.types.len()),
+        }
+    }
+}
+
+fn button_on_press_lobby_system(
+    interaction_query: Query<(&Interaction, &LobbyButton), (Changed<Interaction>, With<Button>)>,
+    tablet_assets: Res<TabletAssets>,
+    mut lobby: ResMut<Lobby>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    for (interaction, lobby_button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *lobby_button {
+            LobbyButton::Color(slot) => lobby.cycle_color(slot, true),
+            LobbyButton::Tablet(slot) => lobby.cycle_tablet(slot, tablet_assets.types.len()),
+            LobbyButton::Bot(slot) => lobby.toggle_bot(slot),
+            LobbyButton::Team(slot) => lobby.cycle_team(slot),
+            LobbyButton::Leave(slot) => {
+                lobby.slots.remove(slot);
+            }
+            LobbyButton::TeamMatch => lobby.team_match = !lobby.team_match,
+            LobbyButton::FriendlyFire => lobby.friendly_fire = !lobby.friendly_fire,
+            LobbyButton::AddBot => lobby.join(None),
+            LobbyButton::Back => menu_state.set(MainMenuState::PlayerMenu),
+        }
+    }
+}
+
+fn spawn_slot_card(
+    parent: &mut ChildBuilder,
+    font_assets: &Res<FontAssets>,
+    player: usize,
+    slot: &LobbySlot,
+    label: &str,
+    tablet_type: &TabletType,
+    team_match: bool,
+) {
+    let text_font = TextFont {
+        font: font_assets.bold.clone(),
+        font_size: 33.0,
+        ..default()
+    };
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.),
+            ..default()
+        })
+        .with_children(|card| {
+            card.spawn((Text::new(label), text_font.clone(), TextColor(TEXT_COLOR)));
+            card.spawn((
+                Node {
+                    width: Val::Px(160.),
+                    height: Val::Px(20.),
+                    ..default()
+                },
+                BackgroundColor(Color::from(PLAYER_PALETTE[slot.color])),
+            ));
+            create_button(card, font_assets, "Colour", LobbyButton::Color(player));
+            create_button(
+                card,
+                font_assets,
+                &tablet_type.name,
+                LobbyButton::Tablet(player),
+            );
+            if slot.device.is_some() {
+                let kind = if slot.bot { "Bot" } else { "Human" };
+                create_button(card, font_assets, kind, LobbyButton::Bot(player));
+            }
+            if team_match {
+                let team = format!("Team {}", slot.team + 1);
+                create_button(card, font_assets, &team, LobbyButton::Team(player));
+            }
+            create_button(card, font_assets, "Leave", LobbyButton::Leave(player));
+            let status = if slot.ready { "Ready" } else { "Waiting" };
+            card.spawn((Text::new(status), text_font, TextColor(TEXT_COLOR)));
+        });
+}
+
+// the layout is spawned on the first frame, then rebuilt along with the lobby
+fn refresh_lobby(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    lobby: Res<Lobby>,
+    controls: Res<PlayerControls>,
+    tablet_assets: Res<TabletAssets>,
+    tablet_types: Res<Assets<TabletType>>,
+    canvas_query: Query<Entity, With<MenuCanvas>>,
+    panel_query: Query<Entity, With<LobbySlotsPanel>>,
+) {
+    let row = Node {
+        column_gap: Val::Px(30.),
+        ..default()
+    };
+    let panel = match panel_query.get_single() {
+        Ok(panel) if lobby.is_changed() => panel,
+        Ok(_) => return,
+        Err(_) => {
+            let Ok(canvas) = canvas_query.get_single() else {
+                return;
+            };
+            let mut panel = None;
+            commands.entity(canvas).with_children(|menu_parent| {
+                menu_parent
+                    .spawn((
+                        LobbyLayout,
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(30.),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|layout| {
+                        layout.spawn((
+                            Text::new("Press a thruster to join, Up to get ready"),
+                            TextFont {
+                                font: font_assets.bold.clone(),
+                                font_size: 33.0,
+                                ..default()
+                            },
+                            TextColor(TEXT_COLOR),
+                        ));
+                        panel = Some(
+                            layout
+                                .spawn((
+                                    LobbySlotsPanel,
+                                    Node {
+                                        flex_direction: FlexDirection::Column,
+                                        align_items: AlignItems::Center,
+                                        row_gap: Val::Px(30.),
+                                        ..default()
+                                    },
+                                ))
+                                .id(),
+                        );
+                        layout.spawn(row.clone()).with_children(|buttons| {
+                            create_button(buttons, &font_assets, "Add bot", LobbyButton::AddBot);
+                            create_button(buttons, &font_assets, "Back", LobbyButton::Back);
+                        });
+                    });
+            });
+            let Some(panel) = panel else {
+                return;
+            };
+            panel
+        }
+    };
+
+    commands
+        .entity(panel)
+        .despawn_descendants()
+        .with_children(|panel| {
+            panel.spawn(row.clone()).with_children(|cards| {
+                for (player, slot) in lobby.slots.iter().enumerate() {
+                    let tablet_type = tablet_assets.get(slot.tablet, &tablet_types);
+                    let label = slot_label(player, slot, &controls);
+                    spawn_slot_card(
+                        cards,
+                        &font_assets,
+                        player,
+                        slot,
+                        &label,
+                        &tablet_type,
+                        lobby.team_match,
+                    );
+                }
+            });
+            panel.spawn(row).with_children(|options| {
+                create_button(
+                    options,
+                    &font_assets,
+                    &toggle_label("Teams", lobby.team_match),
+                    LobbyButton::TeamMatch,
+                );
+                if lobby.team_match {
+                    create_button(
+                        options,
+                        &font_assets,
+                        &toggle_label("Ally hit", lobby.friendly_fire),
+                        LobbyButton::FriendlyFire,
+                    );
+                }
+            });
+        });
+}
+
+fn despawn_lobby(mut commands: Commands, query: Query<Entity, With<LobbyLayout>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn start_when_ready(
+    mut commands: Commands,
+    lobby: Res<Lobby>,
+    match_settings: Res<MatchSettings>,
+    hazard_settings: Res<HazardSettings>,
+    arena_choice: Res<ArenaChoice>,
+    arena_assets: Res<ArenaAssets>,
+    arenas: Res<Assets<Arena>>,
+    tablet_assets: Res<TabletAssets>,
+    tablet_types: Res<Assets<TabletType>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if !lobby.is_changed() || !lobby.is_ready() {
+        return;
+    }
+    let slots = &lobby.slots;
+    commands.insert_resource(PlayerNumber(slots.len()));
+    commands.insert_resource(BotPlayers(slots.iter().map(|slot| slot.bot).collect()));
+    commands.insert_resource(PlayerDevices(
+        slots
+            .iter()
+            .map(|slot| slot.device.filter(|_| !slot.bot))
+            .collect(),
+    ));
+    commands.insert_resource(PlayerColors(
+        slots
+            .iter()
+            .map(|slot| PLAYER_PALETTE[slot.color])
+            .collect(),
+    ));
+    commands.insert_resource(PlayerTablets(
+        slots
+            .iter()
+            .map(|slot| tablet_assets.get(slot.tablet, &tablet_types))
+            .collect(),
+    ));
+    let teams = lobby.teams();
+    commands.insert_resource(MatchScore::new(
+        teams.side_count(slots.len()),
+        match_settings.best_of,
+    ));
+    commands.insert_resource(teams);
+    commands.insert_resource(arena_choice.arena(&arena_assets, &arenas));
+    commands.insert_resource(hazard_settings.0);
+    app_state.set(AppState::InGame);
+}
@@ -1,21 +1,36 @@
 use bevy::{
     app::{AppExit, Plugin, Update},
-    asset::AssetServer,
+    asset::Assets,
     color::{Alpha, Color},
+    input::ButtonInput,
+    log::warn,
     prelude::{
-        in_state, BuildChildren, Button, Changed, ChildBuild, ChildBuilder, Commands, Component,
-        DespawnRecursiveExt, Entity, EntityCommands, EventWriter, ImageNode, IntoSystemConfigs,
-        NextState, OnEnter, OnExit, Query, Res, ResMut, Text, With,
+        in_state, resource_exists, BuildChildren, Button, Changed, ChildBuild, ChildBuilder,
+        Children, Commands, Component, Condition, DespawnRecursiveExt, DetectChanges, Entity,
+        EntityCommands, EventWriter, ImageNode, IntoSystemConfigs, KeyCode, NextState, OnEnter,
+        OnExit, Query, Res, ResMut, Resource, Text, With, Without,
     },
     text::{TextColor, TextFont},
     ui::{
         AlignContent, AlignItems, BackgroundColor, BorderColor, BorderRadius, BoxShadow,
-        FlexDirection, Interaction, JustifyContent, Node, UiRect, Val,
+        FlexDirection, Interaction, JustifyContent, Node, PositionType, RelativeCursorPosition,
+        UiRect, Val,
     },
     utils::default,
 };
 
-use crate::{AppState, FontAssets, MainMenuState, PlayerNumber, SpriteAssets};
+use crate::{
+    arena::arena_plugin::{Arena, ArenaAssets, ArenaChoice},
+    bot::bot_plugin::BotSettings,
+    game_match::game_match_plugin::MatchSettings,
+    game_tuning::game_tuning_plugin::GameTuning,
+    hazard::hazard_plugin::{toggle_label, HazardSettings},
+    lobby::lobby_plugin::Lobby,
+    player_input::player_input_plugin::{key_label, PlayerControls, Thruster},
+    replay::replay_plugin::{start_playback, Replay},
+    settings::settings_plugin::{GameSettings, VolumeSlider},
+    AppState, FontAssets, MainMenuState, PauseState, SpriteAssets,
+};
 
 use super::{
     BORDER_COLOR, BORDER_PX, BORDER_RADIUS_PIXEL, BUTTON_COLOR, BUTTON_HOVER_COLOR, MENU_COLOR,
@@ -23,17 +38,25 @@ use super::{
 };
 
 #[derive(Component)]
-struct MenuCanvas;
+pub(crate) struct MenuCanvas;
 
 #[derive(Component)]
 enum PlayerMenuButton {
     Training,
-    Two_Player,
-    Three_Player,
-    Four_Player,
+    Play,
+    BestOf,
+    BotDifficulty,
+    Arena,
+    DrainingWater,
+    Spoon,
+    Straw,
     Back,
 }
 
+/// Holds the two button columns of the player menu.
+#[derive(Component)]
+struct PlayerMenuLayout;
+
 #[derive(Component)]
 enum HelpMenu {
     BackButton,
@@ -46,14 +69,44 @@ enum CreditMenu {
     Image,
 }
 
+#[derive(Component)]
+enum ControlsMenu {
+    BackButton,
+    Panel,
+    Binding(usize, Thruster),
+    Status,
+}
+
 #[derive(Component)]
 enum HomeMenuButton {
     Help,
     Credit,
+    Controls,
+    Settings,
+    Replay,
     Start,
     Quit,
 }
 
+#[derive(Component)]
+enum SettingsMenu {
+    BackButton,
+    Panel,
+    Slider(VolumeSlider),
+    SliderFill(VolumeSlider),
+    DisplayMode,
+    Resolution,
+    VSync,
+    PictureInPicture,
+}
+
+// thruster waiting for a key press in the controls menu
+#[derive(Resource)]
+pub(crate) struct PendingRebind {
+    player: usize,
+    thruster: Thruster,
+}
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
@@ -70,20 +123,45 @@ impl Plugin for MainMenuPlugin {
             )
                 .run_if(in_state(AppState::MainMenu)),
         );
+        // key bindings can also be edited from the pause menu
+        app.add_systems(
+            Update,
+            (
+                button_on_press_controls_system,
+                rebind_key_system.run_if(resource_exists::<PendingRebind>),
+                update_binding_labels,
+            )
+                .run_if(in_state(AppState::MainMenu).or(in_state(PauseState::Controls))),
+        );
+        // and so can the audio and display settings
+        app.add_systems(
+            Update,
+            (
+                button_on_press_settings_system,
+                drag_volume_sliders,
+                update_slider_fills,
+            )
+                .chain()
+                .run_if(in_state(AppState::MainMenu).or(in_state(PauseState::Settings))),
+        );
         app.add_systems(OnExit(AppState::MainMenu), despawn_main_menu_fully);
         app.add_systems(OnExit(MainMenuState::HomeMenu), despawn_home_menu);
         app.add_systems(OnExit(MainMenuState::PlayerMenu), despawn_player_menu);
         app.add_systems(OnExit(MainMenuState::Help), despawn_help_menu);
         app.add_systems(OnExit(MainMenuState::Credit), despawn_credit_menu);
+        app.add_systems(OnExit(MainMenuState::Controls), despawn_controls_menu);
+        app.add_systems(OnExit(MainMenuState::Settings), despawn_settings_menu);
 
         app.add_systems(OnEnter(MainMenuState::HomeMenu), spawn_home_menu);
         app.add_systems(OnEnter(MainMenuState::PlayerMenu), spawn_player_menu);
         app.add_systems(OnEnter(MainMenuState::Help), spawn_help_menu);
         app.add_systems(OnEnter(MainMenuState::Credit), spawn_credit_menu);
+        app.add_systems(OnEnter(MainMenuState::Controls), spawn_controls_menu);
+        app.add_systems(OnEnter(MainMenuState::Settings), spawn_settings_menu);
     }
 }
 
-fn create_button<'a, T: Component>(
+pub(crate) fn create_button<'a, T: Component>(
     parent: &'a mut ChildBuilder,
     font_assets: &Res<FontAssets>,
     button_text: &str,
@@ -131,7 +209,7 @@ fn create_button<'a, T: Component>(
     binding
 }
 
-fn create_menu<'a>(parent: &'a mut ChildBuilder) -> EntityCommands<'a> {
+pub(crate) fn create_menu<'a>(parent: &'a mut ChildBuilder) -> EntityCommands<'a> {
     parent.spawn((
         Node {
             padding: UiRect::all(Val::Px(40.)),
@@ -164,7 +242,7 @@ fn create_menu<'a>(parent: &'a mut ChildBuilder) -> EntityCommands<'a> {
     ))
 }
 
-fn button_render_system(
+pub(crate) fn button_render_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>),
@@ -184,11 +262,14 @@ fn button_render_system(
 }
 
 fn button_on_press_home_system(
+    mut commands: Commands,
     mut interaction_query: Query<
         (&Interaction, &HomeMenuButton),
         (Changed<Interaction>, With<Button>),
     >,
+    tuning: Res<GameTuning>,
     mut exit: EventWriter<AppExit>,
+    mut app_state: ResMut<NextState<AppState>>,
     mut menu_state: ResMut<NextState<MainMenuState>>,
 ) {
     for (interaction, menu_button) in &mut interaction_query {
@@ -202,38 +283,98 @@ fn button_on_press_home_system(
                     exit.send(AppExit::Success);
                 }
                 HomeMenuButton::Credit => menu_state.set(MainMenuState::Credit),
+                HomeMenuButton::Controls => menu_state.set(MainMenuState::Controls),
+                HomeMenuButton::Settings => menu_state.set(MainMenuState::Settings),
+                HomeMenuButton::Replay => {
+                    let started = Replay::load()
+                        .is_some_and(|replay| start_playback(&mut commands, replay, &tuning));
+                    if started {
+                        app_state.set(AppState::InGame);
+                    } else {
+                        warn!("No replay recorded yet");
+                    }
+                }
             }
         }
     }
 }
 
+pub(crate) fn set_button_text(
+    children: &Children,
+    text_query: &mut Query<&mut Text>,
+    label: String,
+) {
+    for child in children.iter() {
+        if let Ok(mut text) = text_query.get_mut(*child) {
+            text.0.clone_from(&label);
+        }
+    }
+}
+
 fn button_on_press_players_system(
     mut commands: Commands,
     mut interaction_query: Query<
-        (&Interaction, &PlayerMenuButton),
+        (&Interaction, &PlayerMenuButton, &Children),
         (Changed<Interaction>, With<Button>),
     >,
-    mut app_state: ResMut<NextState<AppState>>,
+    mut text_query: Query<&mut Text>,
+    mut bot_settings: ResMut<BotSettings>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut hazard_settings: ResMut<HazardSettings>,
+    mut arena_choice: ResMut<ArenaChoice>,
+    arena_assets: Res<ArenaAssets>,
+    arenas: Res<Assets<Arena>>,
     mut menu_state: ResMut<NextState<MainMenuState>>,
 ) {
-    for (interaction, menu_button) in &mut interaction_query {
+    for (interaction, menu_button, children) in &mut interaction_query {
         if *interaction == Interaction::Pressed {
             match menu_button {
+                // training opens the lobby with a bot already waiting
                 PlayerMenuButton::Training => {
-                    app_state.set(AppState::InGame);
-                    commands.insert_resource(PlayerNumber(1));
+                    commands.insert_resource(Lobby::with_bots(1));
+                    menu_state.set(MainMenuState::Lobby);
+                }
+                PlayerMenuButton::Play => {
+                    commands.insert_resource(Lobby::default());
+                    menu_state.set(MainMenuState::Lobby);
+                }
+                PlayerMenuButton::BestOf => {
+                    *match_settings = match_settings.next();
+                    set_button_text(children, &mut text_query, match_settings.label());
                 }
-                PlayerMenuButton::Two_Player => {
-                    app_state.set(AppState::InGame);
-                    commands.insert_resource(PlayerNumber(2));
+                PlayerMenuButton::BotDifficulty => {
+                    bot_settings.difficulty = bot_settings.difficulty.next();
+                    set_button_text(
+                        children,
+                        &mut text_query,
+                        bot_settings.difficulty.label().to_string(),
+                    );
                 }
-                PlayerMenuButton::Three_Player => {
-                    app_state.set(AppState::InGame);
-                    commands.insert_resource(PlayerNumber(3));
+                PlayerMenuButton::Arena => {
+                    *arena_choice = arena_choice.next(arena_assets.arenas.len());
+                    set_button_text(
+                        children,
+                        &mut text_query,
+                        arena_choice.label(&arena_assets, &arenas),
+                    );
                 }
-                PlayerMenuButton::Four_Player => {
-                    app_state.set(AppState::InGame);
-                    commands.insert_resource(PlayerNumber(4));
+                PlayerMenuButton::DrainingWater => {
+                    let hazards = &mut hazard_settings.0;
+                    hazards.draining_water = !hazards.draining_water;
+                    let label = toggle_label("Drain", hazards.draining_water);
+                    set_button_text(children, &mut text_query, label);
+                }
+                PlayerMenuButton::Spoon => {
+                    let hazards = &mut hazard_settings.0;
+                    hazards.spoon = !hazards.spoon;
+                    let label = toggle_label("Spoon", hazards.spoon);
+                    set_button_text(children, &mut text_query, label);
+                }
+                PlayerMenuButton::Straw => {
+                    let hazards = &mut hazard_settings.0;
+                    hazards.straw = !hazards.straw;
+                    let label = toggle_label("Straw", hazards.straw);
+                    set_button_text(children, &mut text_query, label);
                 }
                 PlayerMenuButton::Back => menu_state.set(MainMenuState::HomeMenu),
             }
@@ -269,6 +410,187 @@ fn button_on_press_credit_system(
     }
 }
 
+fn button_on_press_controls_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &ControlsMenu),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut status_query: Query<(&ControlsMenu, &mut Text), Without<Button>>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    for (interaction, menu_button) in &mut interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button {
+                ControlsMenu::BackButton => {
+                    commands.remove_resource::<PendingRebind>();
+                    menu_state.set(MainMenuState::HomeMenu);
+                }
+                ControlsMenu::Binding(player, thruster) => {
+                    commands.insert_resource(PendingRebind {
+                        player: *player,
+                        thruster: *thruster,
+                    });
+                    set_controls_status(
+                        &mut status_query,
+                        format!(
+                            "Press a key for P{} {} (Escape to cancel)",
+                            player + 1,
+                            thruster.label()
+                        ),
+                    );
+                }
+                ControlsMenu::Panel | ControlsMenu::Status => (),
+            }
+        }
+    }
+}
+
+fn button_on_press_settings_system(
+    mut interaction_query: Query<
+        (&Interaction, &SettingsMenu, &Children),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<GameSettings>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    for (interaction, menu_button, children) in &mut interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button {
+                SettingsMenu::BackButton => menu_state.set(MainMenuState::HomeMenu),
+                SettingsMenu::DisplayMode => {
+                    settings.display_mode = settings.display_mode.next();
+                    let label = settings.display_mode.label().to_string();
+                    set_button_text(children, &mut text_query, label);
+                }
+                SettingsMenu::Resolution => {
+                    settings.resolution = settings.next_resolution();
+                    set_button_text(children, &mut text_query, settings.resolution_label());
+                }
+                SettingsMenu::VSync => {
+                    settings.vsync = !settings.vsync;
+                    let label = settings.vsync_label().to_string();
+                    set_button_text(children, &mut text_query, label);
+                }
+                SettingsMenu::PictureInPicture => {
+                    settings.picture_in_picture = !settings.picture_in_picture;
+                    let label = settings.picture_in_picture_label().to_string();
+                    set_button_text(children, &mut text_query, label);
+                }
+                SettingsMenu::Panel | SettingsMenu::Slider(_) | SettingsMenu::SliderFill(_) => (),
+            }
+        }
+    }
+}
+
+// a pressed slider follows the cursor until the mouse button is released
+fn drag_volume_sliders(
+    slider_query: Query<(&Interaction, &RelativeCursorPosition, &SettingsMenu)>,
+    mut settings: ResMut<GameSettings>,
+) {
+    for (interaction, cursor, menu) in &slider_query {
+        if let (Interaction::Pressed, SettingsMenu::Slider(slider), Some(position)) =
+            (interaction, menu, cursor.normalized)
+        {
+            let volume = position.x.clamp(0., 1.);
+            if settings.volume(*slider) != volume {
+                settings.set_volume(*slider, volume);
+            }
+        }
+    }
+}
+
+fn update_slider_fills(
+    settings: Res<GameSettings>,
+    mut fill_query: Query<(&SettingsMenu, &mut Node)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (menu, mut node) in &mut fill_query {
+        if let SettingsMenu::SliderFill(slider) = menu {
+            node.width = Val::Percent(settings.volume(*slider) * 100.);
+        }
+    }
+}
+
+fn set_controls_status(
+    status_query: &mut Query<(&ControlsMenu, &mut Text), Without<Button>>,
+    status: String,
+) {
+    for (menu, mut text) in status_query.iter_mut() {
+        if let ControlsMenu::Status = menu {
+            text.0.clone_from(&status);
+        }
+    }
+}
+
+fn rebind_key_system(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    pending: Res<PendingRebind>,
+    mut controls: ResMut<PlayerControls>,
+    mut status_query: Query<(&ControlsMenu, &mut Text), Without<Button>>,
+) {
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+        return;
+    };
+
+    if key == KeyCode::Escape {
+        commands.remove_resource::<PendingRebind>();
+        set_controls_status(&mut status_query, String::new());
+        return;
+    }
+
+    match controls.find(key) {
+        Some((player, thruster)) if player != pending.player || thruster != pending.thruster => {
+            set_controls_status(
+                &mut status_query,
+                format!(
+                    "{} is already used by P{} {}",
+                    key_label(key),
+                    player + 1,
+                    thruster.label()
+                ),
+            );
+        }
+        _ => {
+            controls.0[pending.player].set_key(pending.thruster, key);
+            controls.save();
+            commands.remove_resource::<PendingRebind>();
+            set_controls_status(&mut status_query, String::new());
+        }
+    }
+}
+
+fn update_binding_labels(
+    controls: Res<PlayerControls>,
+    pending: Option<Res<PendingRebind>>,
+    binding_query: Query<(&ControlsMenu, &Children), With<Button>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (menu, children) in &binding_query {
+        if let ControlsMenu::Binding(player, thruster) = menu {
+            let is_pending = pending
+                .as_ref()
+                .is_some_and(|pending| pending.player == *player && pending.thruster == *thruster);
+            let label = if is_pending {
+                "...".to_string()
+            } else {
+                key_label(controls.0[*player].key(*thruster))
+            };
+            for child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(*child) {
+                    if text.0 != label {
+                        text.0.clone_from(&label);
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn setup_main_menu(
     mut commands: Commands,
     sprite_assets: Res<SpriteAssets>,
@@ -316,6 +638,25 @@ fn despawn_credit_menu(
     }
 }
 
+fn despawn_controls_menu(
+    query: Query<Entity, With<ControlsMenu>>, // Query for entities with a `Button` component
+    mut commands: Commands,
+) {
+    commands.remove_resource::<PendingRebind>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive(); // Despawn entity and its children
+    }
+}
+
+fn despawn_settings_menu(
+    query: Query<Entity, With<SettingsMenu>>, // Query for entities with a `Button` component
+    mut commands: Commands,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive(); // Despawn entity and its children
+    }
+}
+
 fn despawn_help_menu(
     query: Query<Entity, With<HelpMenu>>, // Query for entities with a `Button` component
     mut commands: Commands,
@@ -326,7 +667,7 @@ fn despawn_help_menu(
 }
 
 fn despawn_player_menu(
-    query: Query<Entity, With<PlayerMenuButton>>, // Query for entities with a `Button` component
+    query: Query<Entity, With<PlayerMenuLayout>>, // Query for the layout holding every button
     mut commands: Commands,
 ) {
     for entity in query.iter() {
@@ -397,38 +738,296 @@ fn spawn_help_menu(
     }
 }
 
+fn spawn_controls_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<PlayerControls>,
+    query: Query<Entity, With<MenuCanvas>>,
+) {
+    if let Ok(entity) = query.get_single() {
+        spawn_controls_panel(&mut commands, &font_assets, &controls);
+        commands.entity(entity).with_children(|menu_parent| {
+            create_button(menu_parent, &font_assets, "Back", ControlsMenu::BackButton);
+        });
+    }
+}
+
+/// Grid of rebindable keys, one row per player, laid over the top of the screen.
+pub(crate) fn spawn_controls_panel<'a>(
+    commands: &'a mut Commands,
+    font_assets: &Res<FontAssets>,
+    controls: &PlayerControls,
+) -> EntityCommands<'a> {
+    let text_font = TextFont {
+        font: font_assets.bold.clone(),
+        font_size: 33.0,
+        ..default()
+    };
+    let mut panel_root = commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            padding: UiRect::all(Val::Px(40.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ControlsMenu::Panel,
+    ));
+    panel_root.with_children(|parent| {
+        parent
+            .spawn((
+                Node {
+                    padding: UiRect::all(Val::Px(40.)),
+                    border: UiRect::all(Val::Px(BORDER_PX)),
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                BorderColor(BORDER_COLOR),
+                BorderRadius::all(Val::Px(BORDER_RADIUS_PIXEL)),
+                BackgroundColor(MENU_COLOR),
+            ))
+            .with_children(|panel| {
+                for (player, key_map) in controls.0.iter().enumerate() {
+                    panel
+                        .spawn(Node {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(20.),
+                            ..default()
+                        })
+                        .with_children(|row| {
+                            row.spawn((
+                                Text::new(format!("P{}", player + 1)),
+                                text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ));
+                            for thruster in Thruster::ALL {
+                                create_button(
+                                    row,
+                                    font_assets,
+                                    &key_label(key_map.key(thruster)),
+                                    ControlsMenu::Binding(player, thruster),
+                                );
+                            }
+                        });
+                }
+                panel.spawn((
+                    Text::new(""),
+                    text_font.clone(),
+                    TextColor(TEXT_COLOR),
+                    ControlsMenu::Status,
+                ));
+            });
+    });
+    panel_root
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    settings: Res<GameSettings>,
+    query: Query<Entity, With<MenuCanvas>>,
+) {
+    if let Ok(entity) = query.get_single() {
+        spawn_settings_panel(&mut commands, &font_assets, &settings);
+        commands.entity(entity).with_children(|menu_parent| {
+            create_button(menu_parent, &font_assets, "Back", SettingsMenu::BackButton);
+        });
+    }
+}
+
+/// Volume sliders and display choices, laid over the top of the screen.
+pub(crate) fn spawn_settings_panel<'a>(
+    commands: &'a mut Commands,
+    font_assets: &Res<FontAssets>,
+    settings: &GameSettings,
+) -> EntityCommands<'a> {
+    let text_font = TextFont {
+        font: font_assets.bold.clone(),
+        font_size: 33.0,
+        ..default()
+    };
+    let row = Node {
+        align_items: AlignItems::Center,
+        column_gap: Val::Px(20.),
+        ..default()
+    };
+    let row_label = Node {
+        width: Val::Px(220.),
+        ..default()
+    };
+    let mut panel_root = commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            padding: UiRect::all(Val::Px(40.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        SettingsMenu::Panel,
+    ));
+    panel_root.with_children(|parent| {
+        parent
+            .spawn((
+                Node {
+                    padding: UiRect::all(Val::Px(40.)),
+                    border: UiRect::all(Val::Px(BORDER_PX)),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                BorderColor(BORDER_COLOR),
+                BorderRadius::all(Val::Px(BORDER_RADIUS_PIXEL)),
+                BackgroundColor(MENU_COLOR),
+            ))
+            .with_children(|panel| {
+                for slider in VolumeSlider::ALL {
+                    panel.spawn(row.clone()).with_children(|row| {
+                        row.spawn((
+                            Text::new(slider.label()),
+                            text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                            row_label.clone(),
+                        ));
+                        row.spawn((
+                            Interaction::default(),
+                            RelativeCursorPosition::default(),
+                            SettingsMenu::Slider(slider),
+                            Node {
+                                width: Val::Px(340.0),
+                                height: Val::Px(40.0),
+                                border: UiRect::all(Val::Px(BORDER_PX)),
+                                ..default()
+                            },
+                            BorderColor(BORDER_COLOR),
+                            BackgroundColor(BUTTON_COLOR),
+                        ))
+                        .with_child((
+                            SettingsMenu::SliderFill(slider),
+                            Node {
+                                width: Val::Percent(settings.volume(slider) * 100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            BackgroundColor(TEXT_COLOR.with_alpha(0.6)),
+                        ));
+                    });
+                }
+
+                let choices = [
+                    (
+                        "Display",
+                        settings.display_mode.label().to_string(),
+                        SettingsMenu::DisplayMode,
+                    ),
+                    (
+                        "Resolution",
+                        settings.resolution_label(),
+                        SettingsMenu::Resolution,
+                    ),
+                    (
+                        "VSync",
+                        settings.vsync_label().to_string(),
+                        SettingsMenu::VSync,
+                    ),
+                    (
+                        "Picture in picture",
+                        settings.picture_in_picture_label().to_string(),
+                        SettingsMenu::PictureInPicture,
+                    ),
+                ];
+                for (label, value, menu) in choices {
+                    panel.spawn(row.clone()).with_children(|row| {
+                        row.spawn((
+                            Text::new(label),
+                            text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                            row_label.clone(),
+                        ));
+                        create_button(row, font_assets, &value, menu)
+                            .entry::<Node>()
+                            .and_modify(|mut node| node.width = Val::Px(340.));
+                    });
+                }
+            });
+    });
+    panel_root
+}
+
 fn spawn_player_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
+    bot_settings: Res<BotSettings>,
+    match_settings: Res<MatchSettings>,
+    hazard_settings: Res<HazardSettings>,
+    arena_choice: Res<ArenaChoice>,
+    arena_assets: Res<ArenaAssets>,
+    arenas: Res<Assets<Arena>>,
     query: Query<Entity, With<MenuCanvas>>,
 ) {
+    let column = Node {
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(30.),
+        ..default()
+    };
+    let hazards = hazard_settings.0;
     if let Ok(entity) = query.get_single() {
         commands.entity(entity).with_children(|menu_parent| {
-            create_button(
-                menu_parent,
-                &font_assets,
-                "Training",
-                PlayerMenuButton::Training,
-            );
-            create_button(
-                menu_parent,
-                &font_assets,
-                "2 Players",
-                PlayerMenuButton::Two_Player,
-            );
-            create_button(
-                menu_parent,
-                &font_assets,
-                "3 Players",
-                PlayerMenuButton::Three_Player,
-            );
-            create_button(
-                menu_parent,
-                &font_assets,
-                "4 Players",
-                PlayerMenuButton::Four_Player,
-            );
-            create_button(menu_parent, &font_assets, "Back", PlayerMenuButton::Back);
+            // match modes on the left, match options on the right
+            menu_parent
+                .spawn((
+                    PlayerMenuLayout,
+                    Node {
+                        column_gap: Val::Px(30.),
+                        ..default()
+                    },
+                ))
+                .with_children(|layout| {
+                    layout.spawn(column.clone()).with_children(|modes| {
+                        create_button(modes, &font_assets, "Training", PlayerMenuButton::Training);
+                        create_button(modes, &font_assets, "Play", PlayerMenuButton::Play);
+                        create_button(modes, &font_assets, "Back", PlayerMenuButton::Back);
+                    });
+                    layout.spawn(column).with_children(|options| {
+                        create_button(
+                            options,
+                            &font_assets,
+                            &match_settings.label(),
+                            PlayerMenuButton::BestOf,
+                        );
+                        create_button(
+                            options,
+                            &font_assets,
+                            bot_settings.difficulty.label(),
+                            PlayerMenuButton::BotDifficulty,
+                        );
+                        create_button(
+                            options,
+                            &font_assets,
+                            &arena_choice.label(&arena_assets, &arenas),
+                            PlayerMenuButton::Arena,
+                        );
+                        create_button(
+                            options,
+                            &font_assets,
+                            &toggle_label("Drain", hazards.draining_water),
+                            PlayerMenuButton::DrainingWater,
+                        );
+                        create_button(
+                            options,
+                            &font_assets,
+                            &toggle_label("Spoon", hazards.spoon),
+                            PlayerMenuButton::Spoon,
+                        );
+                        create_button(
+                            options,
+                            &font_assets,
+                            &toggle_label("Straw", hazards.straw),
+                            PlayerMenuButton::Straw,
+                        );
+                    });
+                });
         });
     }
 }
@@ -443,6 +1042,19 @@ fn spawn_home_menu(
         commands.entity(entity).with_children(|menu_parent| {
             create_button(menu_parent, &font_assets, "Start", HomeMenuButton::Start);
             create_button(menu_parent, &font_assets, "Help", HomeMenuButton::Help);
+            create_button(
+                menu_parent,
+                &font_assets,
+                "Controls",
+                HomeMenuButton::Controls,
+            );
+            create_button(
+                menu_parent,
+                &font_assets,
+                "Settings",
+                HomeMenuButton::Settings,
+            );
+            create_button(menu_parent, &font_assets, "Replay", HomeMenuButton::Replay);
             create_button(menu_parent, &font_assets, "Credit", HomeMenuButton::Credit);
             cfg_if::cfg_if! {
                 if #[cfg(not(target_arch = "wasm32"))] {
@@ -472,6 +1084,19 @@ fn spawn_home_menu(
                 create_menu(parent).with_children(|menu_parent| {
                     create_button(menu_parent, &font_assets, "Start", HomeMenuButton::Start);
                     create_button(menu_parent, &font_assets, "Help", HomeMenuButton::Help);
+                    create_button(
+                        menu_parent,
+                        &font_assets,
+                        "Controls",
+                        HomeMenuButton::Controls,
+                    );
+                    create_button(
+                        menu_parent,
+                        &font_assets,
+                        "Settings",
+                        HomeMenuButton::Settings,
+                    );
+                    create_button(menu_parent, &font_assets, "Replay", HomeMenuButton::Replay);
                     create_button(menu_parent, &font_assets, "Credit", HomeMenuButton::Credit);
                     cfg_if::cfg_if! {
                         if #[cfg(not(target_arch = "wasm32"))] {
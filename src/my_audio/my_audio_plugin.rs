@@ -1,343 +1,302 @@
 use bevy::{
     app::{Plugin, Update},
-    asset::{AssetServer, Handle},
-    input::ButtonInput,
+    asset::{Assets, Handle},
     prelude::{
-        in_state, Component, IntoSystemConfigs, KeyCode, OnEnter, Query, Res, Resource, Transform,
+        in_state, Commands, Component, DetectChanges, Entity, EventReader, IntoSystemConfigs,
+        OnEnter, OnExit, Query, Res, ResMut, Resource, State, Transform, With, Without,
     },
 };
 use bevy_asset_loader::asset_collection::AssetCollection;
-use bevy_kira_audio::{Audio, AudioApp, AudioChannel, AudioControl, AudioSource};
+use bevy_kira_audio::{
+    AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, AudioTween,
+};
+
+use rand::Rng;
 
-use crate::{is_in_water, AppState, Player, PLAYER_CONTROL};
+use crate::{
+    on_hit::on_hit_plugin::HitEvent,
+    pickup::pickup_plugin::{IceCubeLanded, PickupEvent, PickupKind},
+    settings::settings_plugin::GameSettings,
+    water::water_plugin::Water,
+    AppState, Health, PauseState, Player, PlayerIntent,
+};
 
 pub struct MyAudioPlugin;
 
-#[derive(Resource, Component, Default, Clone)]
-pub struct GlassChannel;
+const MENU_MUSIC_VOLUME: f64 = 1.0;
+const GAME_MUSIC_VOLUME: f64 = 1.5;
+const EFFERVESCENT_VOLUME: f64 = 0.08;
+const TURBO_VOLUME: f64 = 0.5;
 
 #[derive(Resource, Component, Default, Clone)]
-struct TurboChannel1p1;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel2p1;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel1p2;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel2p2;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel1p3;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel2p3;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel1p4;
-#[derive(Resource, Component, Default, Clone)]
-struct TurboChannel2p4;
+struct SongChannel;
 
 #[derive(Resource, Component, Default, Clone)]
-struct EffervescentChannelp1;
-#[derive(Resource, Component, Default, Clone)]
-struct EffervescentChannelp2;
-#[derive(Resource, Component, Default, Clone)]
-struct EffervescentChannelp3;
-#[derive(Resource, Component, Default, Clone)]
-struct EffervescentChannelp4;
+struct SfxChannel;
 
+// every looping sound of the tablets, so they can all be cut at once
 #[derive(Resource, Component, Default, Clone)]
-struct SongChannel;
+struct PlayerLoopChannel;
 
-#[derive(Resource, Component, Default, Clone)]
-pub struct PlayerChannel;
+/// Sound looping on a tablet for as long as `is_active` holds.
+struct PlayerLoop {
+    sound: fn(&AudioAssets, &Player) -> Handle<AudioSource>,
+    volume: f64,
+    loop_from: f64,
+    // one shot played every time the loop starts
+    start_sound: Option<fn(&AudioAssets) -> Handle<AudioSource>>,
+    is_active: fn(&Water, &Transform, &PlayerIntent) -> bool,
+}
 
-fn play_menu_music(audio_assets: Res<AudioAssets>, audio: Res<AudioChannel<SongChannel>>) {
-    if audio.is_playing_sound() {
-        audio.stop();
-    }
-    audio
-        .play(audio_assets.in_menu_theme.clone())
-        .with_volume(1.0)
-        .looped();
+fn is_thrusting_left(water: &Water, transform: &Transform, intent: &PlayerIntent) -> bool {
+    water.contains(&transform.translation)
+        && (intent.up > 0. || intent.down > 0. || intent.left > 0.)
 }
 
-fn play_game_music(audio_assets: Res<AudioAssets>, audio: Res<AudioChannel<SongChannel>>) {
-    if audio.is_playing_sound() {
-        audio.stop();
-    }
-    audio
-        .play(audio_assets.in_game_theme.clone())
-        .with_volume(1.5)
-        .looped();
+fn is_thrusting_right(water: &Water, transform: &Transform, intent: &PlayerIntent) -> bool {
+    water.contains(&transform.translation)
+        && (intent.up > 0. || intent.down > 0. || intent.right > 0.)
 }
 
-fn play_effervescent_sound(
+const PLAYER_LOOPS: [PlayerLoop; 3] = [
+    // fizzing while dissolving, neighbours get a different sample
+    PlayerLoop {
+        sound: |assets, player| {
+            if player.0 % 2 == 0 {
+                assets.effer_1.clone()
+            } else {
+                assets.effer_2.clone()
+            }
+        },
+        volume: EFFERVESCENT_VOLUME,
+        loop_from: 0.,
+        start_sound: None,
+        is_active: |water, transform, _| water.contains(&transform.translation),
+    },
+    PlayerLoop {
+        sound: |assets, _| assets.boost_1.clone(),
+        volume: TURBO_VOLUME,
+        loop_from: 0.75,
+        start_sound: Some(|assets| assets.boost_explosion.clone()),
+        is_active: is_thrusting_left,
+    },
+    PlayerLoop {
+        sound: |assets, _| assets.boost_2.clone(),
+        volume: TURBO_VOLUME,
+        loop_from: 0.75,
+        start_sound: Some(|assets| assets.boost_explosion.clone()),
+        is_active: is_thrusting_right,
+    },
+];
+
+/// Playing instance of each entry of `PLAYER_LOOPS` for one tablet.
+#[derive(Component)]
+struct PlayerSounds([Option<Handle<AudioInstance>>; PLAYER_LOOPS.len()]);
+
+fn play_hit_sound(
     audio_assets: Res<AudioAssets>,
-    audio1: Res<AudioChannel<EffervescentChannelp1>>,
-    audio2: Res<AudioChannel<EffervescentChannelp2>>,
-    audio3: Res<AudioChannel<EffervescentChannelp3>>,
-    audio4: Res<AudioChannel<EffervescentChannelp4>>,
-    in_water_object: Query<(&Transform, &Player)>,
+    settings: Res<GameSettings>,
+    audio: Res<AudioChannel<SfxChannel>>,
+    mut hit_events: EventReader<HitEvent>,
 ) {
-    for (transform, player) in &in_water_object {
-        if is_in_water(&transform.translation) {
-            match player.0 {
-                0 => {
-                    if !audio1.is_playing_sound() {
-                        audio1
-                            .play(audio_assets.effer_1.clone())
-                            .with_volume(0.08)
-                            .looped();
-                    }
-                }
-                1 => {
-                    if !audio2.is_playing_sound() {
-                        audio2
-                            .play(audio_assets.effer_2.clone())
-                            .with_volume(0.08)
-                            .looped();
-                    }
-                }
-                2 => {
-                    if !audio3.is_playing_sound() {
-                        audio3
-                            .play(audio_assets.effer_1.clone())
-                            .with_volume(0.08)
-                            .looped();
-                    }
-                }
-                3 => {
-                    if !audio4.is_playing_sound() {
-                        audio4
-                            .play(audio_assets.effer_2.clone())
-                            .with_volume(0.08)
-                            .looped();
-                    }
-                }
-                _ => (),
+    let mut rng = rand::rng();
+    for hit in hit_events.read() {
+        match hit {
+            HitEvent::TabletClash => {
+                let sound = match rng.random_range(1..=3) {
+                    1 => &audio_assets.tabshock_1,
+                    2 => &audio_assets.tabshock_2,
+                    _ => &audio_assets.tabshock_3,
+                };
+                audio.play(sound.clone()).with_volume(settings.sfx_gain());
             }
-        } else {
-            match player.0 {
-                0 => {
-                    if audio1.is_playing_sound() {
-                        audio1.stop();
-                    }
-                }
-                1 => {
-                    if audio2.is_playing_sound() {
-                        audio2.stop();
-                    }
-                }
-                2 => {
-                    if audio3.is_playing_sound() {
-                        audio3.stop();
-                    }
-                }
-                3 => {
-                    if audio4.is_playing_sound() {
-                        audio4.stop();
-                    }
-                }
-                _ => (),
+            HitEvent::GlassImpact => {
+                let sound = match rng.random_range(1..=2) {
+                    1 => &audio_assets.impact_glass_1,
+                    _ => &audio_assets.impact_glass_2,
+                };
+                audio.play(sound.clone()).with_volume(settings.sfx_gain());
             }
         }
     }
 }
 
-fn play_turbo_sound<T: Resource, P: Resource>(
-    audio_assets: &Res<AudioAssets>,
-    keyboard_input: &Res<ButtonInput<KeyCode>>,
-    audio: &Res<Audio>,
-    audio1: &Res<AudioChannel<T>>,
-    audio2: &Res<AudioChannel<P>>,
-    transform: &Transform,
-    player: &Player,
+fn play_pickup_sound(
+    audio_assets: Res<AudioAssets>,
+    settings: Res<GameSettings>,
+    audio: Res<AudioChannel<SfxChannel>>,
+    mut pickup_events: EventReader<PickupEvent>,
+    mut landed_events: EventReader<IceCubeLanded>,
 ) {
-    if is_in_water(&transform.translation) {
-        if keyboard_input.pressed(PLAYER_CONTROL[player.0].up)
-            || keyboard_input.pressed(PLAYER_CONTROL[player.0].down)
-            || (keyboard_input.pressed(PLAYER_CONTROL[player.0].left)
-                && keyboard_input.pressed(PLAYER_CONTROL[player.0].right))
-        {
-            if !audio1.is_playing_sound() {
-                audio.play(audio_assets.boost_explosion.clone());
-                audio1
-                    .play(audio_assets.boost_1.clone())
-                    .loop_from(0.75)
-                    .with_volume(0.5);
-            }
-            if !audio2.is_playing_sound() {
-                audio.play(audio_assets.boost_explosion.clone());
-                audio2
-                    .play(audio_assets.boost_2.clone())
-                    .loop_from(0.75)
-                    .with_volume(0.5);
-            }
-        } else if keyboard_input.pressed(PLAYER_CONTROL[player.0].left) {
-            if !audio1.is_playing_sound() {
-                audio.play(audio_assets.boost_explosion.clone());
-                audio1
-                    .play(audio_assets.boost_1.clone())
-                    .loop_from(0.75)
-                    .with_volume(0.5);
-            }
-            if audio2.is_playing_sound() {
-                audio2.stop();
-            }
-        } else if keyboard_input.pressed(PLAYER_CONTROL[player.0].right) {
-            if !audio2.is_playing_sound() {
-                audio.play(audio_assets.boost_explosion.clone());
-                audio2
-                    .play(audio_assets.boost_2.clone())
-                    .loop_from(0.75)
-                    .with_volume(0.5);
-            }
-            if audio1.is_playing_sound() {
-                audio1.stop();
-            }
-        } else {
-            if audio1.is_playing_sound() {
-                audio1.stop();
-            }
-            if audio2.is_playing_sound() {
-                audio2.stop();
-            }
-        }
-    } else {
-        if audio1.is_playing_sound() {
-            audio1.stop();
-        }
-        if audio2.is_playing_sound() {
-            audio2.stop();
-        }
+    for pickup in pickup_events.read() {
+        let sound = match pickup.kind {
+            PickupKind::SugarCube => &audio_assets.pickup_sugar,
+            PickupKind::LemonSlice => &audio_assets.pickup_lemon,
+            // nobody can grab it, it is heard when it falls in
+            PickupKind::IceCube => continue,
+        };
+        audio.play(sound.clone()).with_volume(settings.sfx_gain());
+    }
+    for _ in landed_events.read() {
+        audio
+            .play(audio_assets.ice_plop.clone())
+            .with_volume(settings.sfx_gain());
     }
 }
 
-fn play_turbo_sound1(
+// music is played at the channel volume, so a settings change applies to the playing track
+fn play_menu_music(
     audio_assets: Res<AudioAssets>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    audio: Res<Audio>,
-    audio1: Res<AudioChannel<TurboChannel1p1>>,
-    audio2: Res<AudioChannel<TurboChannel2p1>>,
-    in_water_object: Query<(&Transform, &Player)>,
+    settings: Res<GameSettings>,
+    audio: Res<AudioChannel<SongChannel>>,
 ) {
-    for (transform, player) in &in_water_object {
-        if player.0 == 0 {
-            play_turbo_sound::<TurboChannel1p1, TurboChannel2p1>(
-                &audio_assets,
-                &keyboard_input,
-                &audio,
-                &audio1,
-                &audio2,
-                transform,
-                player,
-            );
-            break;
-        }
+    if audio.is_playing_sound() {
+        audio.stop();
     }
+    audio.set_volume(MENU_MUSIC_VOLUME * settings.music_gain());
+    audio.play(audio_assets.in_menu_theme.clone()).looped();
 }
 
-fn play_turbo_sound2(
+fn play_game_music(
     audio_assets: Res<AudioAssets>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    audio: Res<Audio>,
-    audio1: Res<AudioChannel<TurboChannel1p2>>,
-    audio2: Res<AudioChannel<TurboChannel2p2>>,
-    in_water_object: Query<(&Transform, &Player)>,
+    settings: Res<GameSettings>,
+    audio: Res<AudioChannel<SongChannel>>,
 ) {
-    for (transform, player) in &in_water_object {
-        if player.0 == 1 {
-            play_turbo_sound::<TurboChannel1p2, TurboChannel2p2>(
-                &audio_assets,
-                &keyboard_input,
-                &audio,
-                &audio1,
-                &audio2,
-                transform,
-                player,
-            );
-            break;
-        }
+    if audio.is_playing_sound() {
+        audio.stop();
     }
+    audio.set_volume(GAME_MUSIC_VOLUME * settings.music_gain());
+    audio.play(audio_assets.in_game_theme.clone()).looped();
 }
 
-fn play_turbo_sound3(
+fn apply_music_volume(
+    settings: Res<GameSettings>,
+    app_state: Res<State<AppState>>,
+    audio: Res<AudioChannel<SongChannel>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let volume = match app_state.get() {
+        AppState::InGame => GAME_MUSIC_VOLUME,
+        _ => MENU_MUSIC_VOLUME,
+    };
+    audio.set_volume(volume * settings.music_gain());
+}
+
+fn attach_player_sounds(
+    mut commands: Commands,
+    query: Query<Entity, (With<Player>, Without<PlayerSounds>)>,
+) {
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .insert(PlayerSounds(std::array::from_fn(|_| None)));
+    }
+}
+
+fn play_player_loops(
     audio_assets: Res<AudioAssets>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    audio: Res<Audio>,
-    audio1: Res<AudioChannel<TurboChannel1p3>>,
-    audio2: Res<AudioChannel<TurboChannel2p3>>,
-    in_water_object: Query<(&Transform, &Player)>,
+    settings: Res<GameSettings>,
+    water: Res<Water>,
+    audio: Res<AudioChannel<PlayerLoopChannel>>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    mut query: Query<(
+        &Transform,
+        &Player,
+        &PlayerIntent,
+        &Health,
+        &mut PlayerSounds,
+    )>,
 ) {
-    for (transform, player) in &in_water_object {
-        if player.0 == 2 {
-            play_turbo_sound::<TurboChannel1p3, TurboChannel2p3>(
-                &audio_assets,
-                &keyboard_input,
-                &audio,
-                &audio1,
-                &audio2,
-                transform,
-                player,
-            );
-            break;
+    for (transform, player, intent, health, mut sounds) in &mut query {
+        for (player_loop, instance) in PLAYER_LOOPS.iter().zip(sounds.0.iter_mut()) {
+            // silenced before the dissolved tablet is despawned with its handles
+            let is_active = health.0 > 0. && (player_loop.is_active)(&water, transform, intent);
+            match (is_active, instance.take()) {
+                (true, Some(playing)) => *instance = Some(playing),
+                (true, None) => {
+                    if let Some(start_sound) = player_loop.start_sound {
+                        sfx.play(start_sound(&audio_assets))
+                            .with_volume(settings.sfx_gain());
+                    }
+                    *instance = Some(
+                        audio
+                            .play((player_loop.sound)(&audio_assets, player))
+                            .loop_from(player_loop.loop_from)
+                            .with_volume(player_loop.volume * settings.sfx_gain())
+                            .handle(),
+                    );
+                }
+                (false, Some(playing)) => {
+                    if let Some(playing) = instances.get_mut(&playing) {
+                        playing.stop(AudioTween::default());
+                    }
+                }
+                (false, None) => (),
+            }
         }
     }
 }
 
-fn play_turbo_sound4(
-    audio_assets: Res<AudioAssets>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    audio: Res<Audio>,
-    audio1: Res<AudioChannel<TurboChannel1p4>>,
-    audio2: Res<AudioChannel<TurboChannel2p4>>,
-    in_water_object: Query<(&Transform, &Player)>,
+// loops are played at their own volume, so a settings change is passed on to the playing ones
+fn apply_loop_volume(
+    settings: Res<GameSettings>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    query: Query<&PlayerSounds>,
 ) {
-    for (transform, player) in &in_water_object {
-        if player.0 == 3 {
-            play_turbo_sound::<TurboChannel1p4, TurboChannel2p4>(
-                &audio_assets,
-                &keyboard_input,
-                &audio,
-                &audio1,
-                &audio2,
-                transform,
-                player,
-            );
-            break;
+    if !settings.is_changed() {
+        return;
+    }
+    for sounds in &query {
+        for (player_loop, instance) in PLAYER_LOOPS.iter().zip(sounds.0.iter()) {
+            if let Some(playing) = instance
+                .as_ref()
+                .and_then(|handle| instances.get_mut(handle))
+            {
+                playing.set_volume(
+                    player_loop.volume * settings.sfx_gain(),
+                    AudioTween::default(),
+                );
+            }
         }
     }
 }
 
+// loops are restarted by `play_player_loops` once the round runs again
+fn stop_player_loops(
+    audio: Res<AudioChannel<PlayerLoopChannel>>,
+    mut query: Query<&mut PlayerSounds>,
+) {
+    audio.stop();
+    for mut sounds in &mut query {
+        sounds.0 = std::array::from_fn(|_| None);
+    }
+}
+
 impl Plugin for MyAudioPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_audio_channel::<SongChannel>();
-        app.add_audio_channel::<GlassChannel>();
-        app.add_audio_channel::<TurboChannel1p1>();
-        app.add_audio_channel::<TurboChannel2p1>();
-        app.add_audio_channel::<TurboChannel1p2>();
-        app.add_audio_channel::<TurboChannel2p2>();
-        app.add_audio_channel::<TurboChannel1p3>();
-        app.add_audio_channel::<TurboChannel2p3>();
-        app.add_audio_channel::<TurboChannel1p4>();
-        app.add_audio_channel::<TurboChannel2p4>();
-        app.add_audio_channel::<EffervescentChannelp1>();
-        app.add_audio_channel::<EffervescentChannelp2>();
-        app.add_audio_channel::<EffervescentChannelp3>();
-        app.add_audio_channel::<EffervescentChannelp4>();
-        app.add_audio_channel::<PlayerChannel>();
+        app.add_audio_channel::<SfxChannel>();
+        app.add_audio_channel::<PlayerLoopChannel>();
 
         app.add_systems(OnEnter(AppState::InGame), play_game_music);
         app.add_systems(OnEnter(AppState::MainMenu), play_menu_music);
+        app.add_systems(Update, (apply_music_volume, apply_loop_volume));
 
         app.add_systems(
             Update,
             (
-                play_turbo_sound1,
-                play_turbo_sound2,
-                play_turbo_sound3,
-                play_turbo_sound4,
-                play_effervescent_sound,
+                attach_player_sounds,
+                play_player_loops,
+                play_hit_sound,
+                play_pickup_sound,
             )
-                .run_if(in_state(AppState::InGame)),
+                .chain()
+                .run_if(in_state(PauseState::Running)),
         );
+        app.add_systems(OnExit(PauseState::Running), stop_player_loops);
     }
 }
 
@@ -361,14 +320,21 @@ pub struct AudioAssets {
     boost_explosion: Handle<AudioSource>,
 
     #[asset(path = "audio/Sfx_tabshock1.wav")]
-    pub tabshock_1: Handle<AudioSource>,
+    tabshock_1: Handle<AudioSource>,
     #[asset(path = "audio/Sfx_tabshock2.wav")]
-    pub tabshock_2: Handle<AudioSource>,
+    tabshock_2: Handle<AudioSource>,
     #[asset(path = "audio/Sfx_tabshock3.wav")]
-    pub tabshock_3: Handle<AudioSource>,
+    tabshock_3: Handle<AudioSource>,
 
     #[asset(path = "audio/Sfx_impactglass1.wav")]
-    pub impact_glass_1: Handle<AudioSource>,
+    impact_glass_1: Handle<AudioSource>,
     #[asset(path = "audio/Sfx_impactglass2.wav")]
-    pub impact_glass_2: Handle<AudioSource>,
+    impact_glass_2: Handle<AudioSource>,
+
+    #[asset(path = "audio/Sfx_pickupSugar.wav")]
+    pickup_sugar: Handle<AudioSource>,
+    #[asset(path = "audio/Sfx_pickupLemon.wav")]
+    pickup_lemon: Handle<AudioSource>,
+    #[asset(path = "audio/Sfx_icePlop.wav")]
+    ice_plop: Handle<AudioSource>,
 }
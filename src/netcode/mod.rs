@@ -0,0 +1 @@
+pub mod netcode_plugin;
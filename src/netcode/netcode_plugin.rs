@@ -0,0 +1,548 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{
+        App, FixedMain, FixedUpdate, Plugin, RunFixedMainLoop, RunFixedMainLoopSystem, Startup,
+        Update,
+    },
+    asset::Assets,
+    log::{error, warn},
+    prelude::{
+        in_state, resource_exists, Commands, Events, IntoSystemConfigs, Mut, NextState, OnEnter,
+        OnExit, Query, Res, ResMut, Resource, State, World,
+    },
+    time::{Fixed, Time, Virtual},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::arena_plugin::{Arena, ArenaAssets, ArenaChoice},
+    bot::bot_plugin::BotPlayers,
+    game_match::game_match_plugin::{MatchScore, MatchSettings},
+    game_tuning::game_tuning_plugin::GameTuning,
+    hazard::hazard_plugin::Hazards,
+    headless_app,
+    lobby::lobby_plugin::PlayerColors,
+    player_input::player_input_plugin::{InputDevice, PlayerDevices},
+    rollback::rollback_plugin::restore,
+    tablet::tablet_plugin::PlayerTablets,
+    use_turbo, AppState, MainMenuState, PauseState, Player, PlayerEliminated, PlayerIntent,
+    PlayerNumber, RoundSeed, Teams,
+};
+
+// ticks the local simulation may run ahead of the last tick every intent arrived for
+const MAX_PREDICTION: usize = 8;
+// resent intents per packet, enough to cover a few lost packets
+const MAX_INTENTS_PER_PACKET: usize = 64;
+const MAX_PACKET_SIZE: usize = 16 * 1024;
+// silence after which a player is taken for gone and the match ends
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `Player` index played on this machine and the address each player of a LAN match listens on.
+#[derive(Resource, Clone, Debug)]
+pub struct NetConfig {
+    pub player: usize,
+    pub addresses: Vec<SocketAddr>,
+}
+
+impl NetConfig {
+    /// Reads `--lan <player> <address of player 1> <address of player 2>...` from the command line,
+    /// for example `--lan 0 127.0.0.1:7000 127.0.0.1:7001` and `--lan 1 ...` for two local processes.
+    pub fn from_args(args: &[String]) -> Option<NetConfig> {
+        let position = args.iter().position(|arg| arg == "--lan")?;
+        let mut values = args[position + 1..]
+            .iter()
+            .take_while(|arg| !arg.starts_with("--"));
+        let player = values.next()?.parse().ok()?;
+        let addresses = values
+            .map(|address| address.parse())
+            .collect::<Result<Vec<SocketAddr>, _>>()
+            .ok()?;
+        (addresses.len() >= 2 && player < addresses.len())
+            .then_some(NetConfig { player, addresses })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct InputPacket {
+    player: usize,
+    // picked by the first player, the others wait for it before their first round
+    seed: Option<u64>,
+    round: u32,
+    first_tick: usize,
+    intents: Vec<PlayerIntent>,
+    // intents of the receiving player that already arrived
+    received: usize,
+}
+
+/// LAN match in progress: only the intents of each player go over UDP.
+/// Every machine plays the round locally with guessed intents for the others and, when a guess
+/// turns out wrong, rolls back to the last tick every intent is known for and plays it again.
+#[derive(Resource)]
+pub struct NetSession {
+    socket: UdpSocket,
+    config: NetConfig,
+    seed: Option<u64>,
+    round: u32,
+    tick: usize,
+    confirmed_tick: usize,
+    // intents of each player since the start of the round, as they played them
+    inputs: Vec<Vec<PlayerIntent>>,
+    // intents the local simulation used for each player, guessed for the ones not arrived yet
+    predicted: Vec<Vec<PlayerIntent>>,
+    // how many local intents each player has received
+    acknowledged: Vec<usize>,
+    mispredicted: bool,
+    rollbacks: usize,
+    // when a packet last arrived from each player
+    last_heard: Vec<Instant>,
+}
+
+impl NetSession {
+    pub fn bind(config: NetConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(config.addresses[config.player])?;
+        socket.set_nonblocking(true)?;
+        let player_number = config.addresses.len();
+        Ok(NetSession {
+            socket,
+            seed: (config.player == 0).then(rand::random),
+            config,
+            round: 0,
+            tick: 0,
+            confirmed_tick: 0,
+            inputs: vec![Vec::new(); player_number],
+            predicted: vec![Vec::new(); player_number],
+            acknowledged: vec![0; player_number],
+            mispredicted: false,
+            rollbacks: 0,
+            last_heard: vec![Instant::now(); player_number],
+        })
+    }
+
+    /// Next tick the local simulation plays.
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// Ticks played with the intents of every player, identical on every machine.
+    pub fn confirmed_tick(&self) -> usize {
+        self.confirmed_tick
+    }
+
+    /// Times the local simulation was rolled back since the session started.
+    pub fn rollbacks(&self) -> usize {
+        self.rollbacks
+    }
+
+    fn player_number(&self) -> usize {
+        self.config.addresses.len()
+    }
+
+    /// Whether a player sent nothing for `PEER_TIMEOUT`, most likely because their game closed.
+    pub fn peer_timed_out(&self) -> bool {
+        self.last_heard
+            .iter()
+            .enumerate()
+            .any(|(player, heard)| player != self.config.player && heard.elapsed() > PEER_TIMEOUT)
+    }
+
+    fn receive(&mut self) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, _)) => {
+                    let packet = std::str::from_utf8(&buffer[..size])
+                        .ok()
+                        .and_then(|text| ron::from_str::<InputPacket>(text).ok());
+                    if let Some(packet) = packet {
+                        self.accept(packet);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // a peer that is not listening yet makes some platforms report an error
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => {
+                    warn!("Could not receive from the other players: {err}");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn accept(&mut self, packet: InputPacket) {
+        if self.seed.is_none() {
+            self.seed = packet.seed;
+        }
+        // a packet of another round still tells the player is there
+        if let Some(heard) = self.last_heard.get_mut(packet.player) {
+            *heard = Instant::now();
+        }
+        if packet.round != self.round
+            || packet.player >= self.player_number()
+            || packet.player == self.config.player
+        {
+            return;
+        }
+        let player = packet.player;
+        self.acknowledged[player] = self.acknowledged[player].max(packet.received);
+        let inputs = &mut self.inputs[player];
+        for (tick, intent) in (packet.first_tick..).zip(packet.intents) {
+            if tick < inputs.len() {
+                continue;
+            }
+            if tick > inputs.len() {
+                break;
+            }
+            if self.predicted[player]
+                .get(tick)
+                .is_some_and(|predicted| *predicted != intent)
+            {
+                self.mispredicted = true;
+            }
+            inputs.push(intent);
+        }
+    }
+
+    // local intents not acknowledged yet, resent until they are
+    fn send(&self) {
+        let local = &self.inputs[self.config.player];
+        for (player, address) in self.config.addresses.iter().enumerate() {
+            if player == self.config.player {
+                continue;
+            }
+            let first_tick = self.acknowledged[player].min(local.len());
+            let packet = InputPacket {
+                player: self.config.player,
+                seed: self.seed,
+                round: self.round,
+                first_tick,
+                intents: local[first_tick..]
+                    .iter()
+                    .take(MAX_INTENTS_PER_PACKET)
+                    .copied()
+                    .collect(),
+                received: self.inputs[player].len(),
+            };
+            let Ok(text) = ron::to_string(&packet) else {
+                continue;
+            };
+            if let Err(err) = self.socket.send_to(text.as_bytes(), address) {
+                if err.kind() != ErrorKind::WouldBlock {
+                    warn!("Could not send to player {}: {err}", player + 1);
+                }
+            }
+        }
+    }
+
+    // intents of every player at the next confirmed tick, once they all arrived
+    fn next_confirmed(&mut self) -> Option<Vec<PlayerIntent>> {
+        let intents = self
+            .inputs
+            .iter()
+            .map(|inputs| inputs.get(self.confirmed_tick).copied())
+            .collect::<Option<Vec<_>>>()?;
+        self.confirmed_tick += 1;
+        Some(intents)
+    }
+
+    // intent of `player` at the tick being played, their last known one stands in for a missing one
+    fn intent(&mut self, player: usize) -> PlayerIntent {
+        let inputs = &self.inputs[player];
+        let intent = inputs
+            .get(self.tick)
+            .or(inputs.last())
+            .copied()
+            .unwrap_or_default();
+        let predicted = &mut self.predicted[player];
+        predicted.truncate(self.tick);
+        predicted.push(intent);
+        intent
+    }
+
+    fn end_round(&mut self) {
+        self.round += 1;
+        self.tick = 0;
+        self.confirmed_tick = 0;
+        self.inputs.iter_mut().for_each(Vec::clear);
+        self.predicted.iter_mut().for_each(Vec::clear);
+        self.acknowledged
+            .iter_mut()
+            .for_each(|received| *received = 0);
+        self.mispredicted = false;
+    }
+}
+
+// the round played with confirmed intents only, what the local world is rolled back to
+struct ConfirmedRound(App);
+
+/// Eliminations of the confirmed round not announced yet.
+/// A predicted one may be undone by a rollback, so a LAN round only announces these.
+#[derive(Resource, Default)]
+pub struct ConfirmedEliminations(pub Vec<PlayerEliminated>);
+
+/// State of the round every player agrees on, `None` outside of a LAN round.
+pub fn confirmed_world(world: &World) -> Option<&World> {
+    world
+        .get_non_send_resource::<ConfirmedRound>()
+        .map(|round| round.0.world())
+}
+
+/// LAN matches between machines given by a `NetConfig`, each playing one tablet.
+pub struct NetcodePlugin;
+
+impl Plugin for NetcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, open_session);
+        app.add_systems(
+            Update,
+            join_lan_match
+                .run_if(in_state(AppState::MainMenu))
+                .run_if(resource_exists::<NetSession>),
+        );
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_confirmed_round.run_if(resource_exists::<NetSession>),
+        );
+        app.add_systems(
+            Update,
+            leave_silent_match
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<NetSession>),
+        );
+        // also runs when the player quit and the session is already gone
+        app.add_systems(OnExit(AppState::InGame), end_confirmed_round);
+        app.add_systems(
+            RunFixedMainLoop,
+            (
+                roll_back.in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                send_intents.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+            )
+                .run_if(resource_exists::<NetSession>),
+        );
+        app.add_systems(
+            FixedUpdate,
+            feed_intents
+                .before(use_turbo)
+                .run_if(in_state(PauseState::Running))
+                .run_if(resource_exists::<NetSession>),
+        );
+    }
+}
+
+fn open_session(mut commands: Commands, config: Option<Res<NetConfig>>) {
+    let Some(config) = config else {
+        return;
+    };
+    match NetSession::bind(config.clone()) {
+        Ok(session) => commands.insert_resource(session),
+        Err(err) => error!(
+            "Could not listen on {}: {err}",
+            config.addresses[config.player]
+        ),
+    }
+}
+
+// every machine plays the same glass with classic tablets, as soon as the seed is known
+fn join_lan_match(
+    mut commands: Commands,
+    session: Res<NetSession>,
+    match_settings: Res<MatchSettings>,
+    arena_assets: Res<ArenaAssets>,
+    arenas: Res<Assets<Arena>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(seed) = session.seed else {
+        return;
+    };
+    let player_number = session.player_number();
+    let mut devices = vec![None; player_number];
+    devices[session.config.player] = Some(InputDevice::Keyboard(0));
+
+    commands.insert_resource(PlayerNumber(player_number));
+    commands.insert_resource(BotPlayers::default());
+    commands.insert_resource(PlayerDevices(devices));
+    commands.insert_resource(PlayerColors::default());
+    commands.insert_resource(PlayerTablets::default());
+    commands.insert_resource(Teams::default());
+    commands.insert_resource(MatchScore::new(player_number, match_settings.best_of));
+    commands.insert_resource(ArenaChoice::default().arena(&arena_assets, &arenas));
+    commands.insert_resource(Hazards::default());
+    commands.insert_resource(RoundSeed(seed.wrapping_add(session.round as u64)));
+    app_state.set(AppState::InGame);
+}
+
+fn start_confirmed_round(world: &mut World) {
+    // the others are given time to enter the round as well
+    let now = Instant::now();
+    world.resource_mut::<NetSession>().last_heard.fill(now);
+    let mut confirmed = headless_app(world.resource::<PlayerNumber>().0);
+    confirmed.insert_resource(world.resource::<GameTuning>().clone());
+    confirmed.insert_resource(world.resource::<Arena>().clone());
+    confirmed.insert_resource(*world.resource::<Hazards>());
+    confirmed.insert_resource(world.resource::<PlayerTablets>().clone());
+    confirmed.insert_resource(world.resource::<Teams>().clone());
+    confirmed.insert_resource(world.resource::<MatchScore>().clone());
+    if let Some(round_seed) = world.get_resource::<RoundSeed>() {
+        confirmed.insert_resource(*round_seed);
+    }
+    // enters the round without playing a tick
+    confirmed.update();
+    world.insert_non_send_resource(ConfirmedRound(confirmed));
+    world.init_resource::<ConfirmedEliminations>();
+}
+
+fn end_confirmed_round(world: &mut World) {
+    if world.remove_non_send_resource::<ConfirmedRound>().is_none() {
+        return;
+    }
+    world.remove_resource::<ConfirmedEliminations>();
+    world.resource_mut::<Time<Virtual>>().unpause();
+    let Some(mut session) = world.get_resource_mut::<NetSession>() else {
+        // the match was left, the next ones are played with a seed of their own
+        world.remove_resource::<RoundSeed>();
+        return;
+    };
+    session.end_round();
+    if let Some(seed) = session.seed {
+        let round_seed = RoundSeed(seed.wrapping_add(session.round as u64));
+        world.insert_resource(round_seed);
+    }
+}
+
+// a player who left or crashed would keep the others waiting for their intents forever
+fn leave_silent_match(
+    mut commands: Commands,
+    session: Res<NetSession>,
+    mut app_state: ResMut<NextState<AppState>>,
+    menu_state: Option<ResMut<NextState<MainMenuState>>>,
+) {
+    if session.peer_timed_out() {
+        warn!("Lost contact with the other players, leaving the LAN match");
+        commands.remove_resource::<NetSession>();
+        app_state.set(AppState::MainMenu);
+        if let Some(mut menu_state) = menu_state {
+            menu_state.set(MainMenuState::HomeMenu);
+        }
+    }
+}
+
+// plays the confirmed round as far as the intents go, and replays the local ticks on a wrong guess
+fn roll_back(world: &mut World) {
+    world.resource_mut::<NetSession>().receive();
+    let Some(mut confirmed) = world.remove_non_send_resource::<ConfirmedRound>() else {
+        return;
+    };
+
+    let running = world
+        .get_resource::<State<PauseState>>()
+        .is_some_and(|state| *state.get() == PauseState::Running);
+    let replay_until = world.resource_scope(|world, mut session: Mut<NetSession>| {
+        while let Some(intents) = session.next_confirmed() {
+            let confirmed_world = confirmed.0.world_mut();
+            let mut query = confirmed_world.query::<(&Player, &mut PlayerIntent)>();
+            for (player, mut intent) in query.iter_mut(confirmed_world) {
+                if let Some(confirmed_intent) = intents.get(player.0) {
+                    *intent = *confirmed_intent;
+                }
+            }
+            confirmed.0.update();
+            let eliminations = confirmed
+                .0
+                .world_mut()
+                .resource_mut::<Events<PlayerEliminated>>()
+                .drain()
+                .collect::<Vec<_>>();
+            if let Some(mut confirmed_eliminations) =
+                world.get_resource_mut::<ConfirmedEliminations>()
+            {
+                confirmed_eliminations.0.extend(eliminations);
+            }
+        }
+        if !running || !session.mispredicted {
+            return None;
+        }
+        session.mispredicted = false;
+        session.rollbacks += 1;
+        let replay_until = session.tick;
+        session.tick = session.confirmed_tick;
+        Some(replay_until)
+    });
+
+    if let Some(replay_until) = replay_until {
+        // what the devices ask for now is not part of the restored state
+        let local = world.resource::<NetSession>().config.player;
+        let live = local_intent(world, local);
+        restore(confirmed.0.world_mut(), world);
+        while world.resource::<NetSession>().tick < replay_until {
+            run_fixed_tick(world);
+        }
+        if let Some(live) = live {
+            set_local_intent(world, local, live);
+        }
+    }
+
+    // waits for the others rather than guessing too far ahead
+    let session = world.resource::<NetSession>();
+    let too_far_ahead = session.tick >= session.confirmed_tick + MAX_PREDICTION;
+    let mut virtual_time = world.resource_mut::<Time<Virtual>>();
+    if too_far_ahead {
+        virtual_time.pause();
+    } else {
+        virtual_time.unpause();
+    }
+
+    world.insert_non_send_resource(confirmed);
+}
+
+fn local_intent(world: &mut World, local: usize) -> Option<PlayerIntent> {
+    let mut query = world.query::<(&Player, &PlayerIntent)>();
+    query
+        .iter(world)
+        .find(|(player, _)| player.0 == local)
+        .map(|(_, intent)| *intent)
+}
+
+fn set_local_intent(world: &mut World, local: usize, live: PlayerIntent) {
+    let mut query = world.query::<(&Player, &mut PlayerIntent)>();
+    for (player, mut intent) in query.iter_mut(world) {
+        if player.0 == local {
+            *intent = live;
+        }
+    }
+}
+
+// same as one step of the fixed main loop, outside of the frame time
+fn run_fixed_tick(world: &mut World) {
+    let timestep = world.resource::<Time<Fixed>>().timestep();
+    world.resource_mut::<Time<Fixed>>().advance_by(timestep);
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    world.run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
+
+fn send_intents(session: Res<NetSession>) {
+    session.send();
+}
+
+// the local intent read from the devices is kept the first time a tick is played
+fn feed_intents(mut session: ResMut<NetSession>, mut query: Query<(&Player, &mut PlayerIntent)>) {
+    let local = session.config.player;
+    if session.inputs[local].len() == session.tick {
+        let live = query
+            .iter()
+            .find(|(player, _)| player.0 == local)
+            .map(|(_, intent)| *intent)
+            .unwrap_or_default();
+        session.inputs[local].push(live);
+    }
+    for (player, mut intent) in &mut query {
+        if player.0 < session.player_number() {
+            *intent = session.intent(player.0);
+        }
+    }
+    session.tick += 1;
+}
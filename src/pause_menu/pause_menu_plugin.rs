@@ -0,0 +1,214 @@
+use bevy::{
+    app::{Plugin, Update},
+    color::{Alpha, Color},
+    input::{
+        gamepad::{Gamepad, GamepadButton},
+        ButtonInput,
+    },
+    prelude::{
+        in_state, not, resource_exists, BuildChildren, Button, Changed, ChildBuild, Commands,
+        Component, DespawnRecursiveExt, Entity, IntoSystemConfigs, KeyCode, NextState, OnEnter,
+        OnExit, Query, Res, ResMut, State, With,
+    },
+    ui::{
+        AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, Node,
+        PositionType, Val,
+    },
+    utils::default,
+};
+
+use crate::{
+    main_menu::main_menu_plugin::{
+        button_render_system, create_button, create_menu, spawn_controls_panel,
+        spawn_settings_panel, PendingRebind,
+    },
+    player_input::player_input_plugin::PlayerControls,
+    settings::settings_plugin::GameSettings,
+    AppState, FontAssets, InGame, MainMenuState, NetSession, PauseState, ThinClient,
+};
+
+#[derive(Component)]
+enum PauseMenuButton {
+    Resume,
+    Restart,
+    Settings,
+    Controls,
+    Quit,
+    Back,
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(
+            Update,
+            toggle_pause
+                .run_if(in_state(AppState::InGame))
+                .run_if(not(resource_exists::<PendingRebind>)),
+        );
+        app.add_systems(
+            Update,
+            (button_render_system, button_on_press_pause_system)
+                .run_if(not(in_state(PauseState::Running)))
+                .run_if(in_state(AppState::InGame)),
+        );
+        app.add_systems(OnEnter(PauseState::Paused), spawn_pause_menu);
+        app.add_systems(OnEnter(PauseState::Settings), spawn_settings_menu);
+        app.add_systems(OnEnter(PauseState::Controls), spawn_controls_menu);
+        app.add_systems(OnExit(PauseState::Paused), despawn_pause_menu);
+        app.add_systems(OnExit(PauseState::Settings), despawn_pause_menu);
+        app.add_systems(OnExit(PauseState::Controls), despawn_pause_menu);
+    }
+}
+
+// Escape or the gamepad Start button goes one level up in the pause menus
+fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let pressed = keyboard_input.just_pressed(KeyCode::Escape)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if !pressed {
+        return;
+    }
+    next_pause_state.set(match pause_state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+        PauseState::Settings | PauseState::Controls => PauseState::Paused,
+    });
+}
+
+fn button_on_press_pause_system(
+    interaction_query: Query<
+        (&Interaction, &PauseMenuButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut commands: Commands,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+    mut pause_state: ResMut<NextState<PauseState>>,
+) {
+    for (interaction, menu_button) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button {
+                PauseMenuButton::Resume => pause_state.set(PauseState::Running),
+                PauseMenuButton::Restart => app_state.set(AppState::RestartRound),
+                PauseMenuButton::Settings => pause_state.set(PauseState::Settings),
+                PauseMenuButton::Controls => pause_state.set(PauseState::Controls),
+                PauseMenuButton::Quit => {
+                    // leaves the server or the LAN match for good, or the main menu would join it again
+                    commands.remove_resource::<ThinClient>();
+                    commands.remove_resource::<NetSession>();
+                    app_state.set(AppState::MainMenu);
+                    menu_state.set(MainMenuState::HomeMenu);
+                }
+                PauseMenuButton::Back => pause_state.set(PauseState::Paused),
+            }
+        }
+    }
+}
+
+// dims the frozen round behind the menu
+fn pause_overlay() -> (InGame, PauseMenu, Node, BackgroundColor) {
+    (
+        InGame,
+        PauseMenu,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.5)),
+    )
+}
+
+// a LAN round only restarts for every player at once, and the rounds of a thin client
+// are the ones the server plays
+fn spawn_pause_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    session: Option<Res<NetSession>>,
+    client: Option<Res<ThinClient>>,
+) {
+    let lan_match = session.is_some();
+    commands.spawn(pause_overlay()).with_children(|parent| {
+        create_menu(parent).with_children(|menu_parent| {
+            create_button(menu_parent, &font_assets, "Resume", PauseMenuButton::Resume);
+            if !lan_match && client.is_none() {
+                create_button(
+                    menu_parent,
+                    &font_assets,
+                    "Restart",
+                    PauseMenuButton::Restart,
+                );
+            }
+            create_button(
+                menu_parent,
+                &font_assets,
+                "Settings",
+                PauseMenuButton::Settings,
+            );
+            create_button(
+                menu_parent,
+                &font_assets,
+                "Controls",
+                PauseMenuButton::Controls,
+            );
+            create_button(menu_parent, &font_assets, "Quit", PauseMenuButton::Quit);
+        });
+    });
+}
+
+// Back button at the bottom, below a panel laid over the top of the screen
+fn spawn_back_button(commands: &mut Commands, font_assets: &Res<FontAssets>) {
+    commands.spawn(pause_overlay()).with_children(|parent| {
+        parent
+            .spawn(Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.),
+                ..default()
+            })
+            .with_children(|bottom| {
+                create_menu(bottom).with_children(|menu_parent| {
+                    create_button(menu_parent, font_assets, "Back", PauseMenuButton::Back);
+                });
+            });
+    });
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    settings: Res<GameSettings>,
+) {
+    spawn_back_button(&mut commands, &font_assets);
+    spawn_settings_panel(&mut commands, &font_assets, &settings).insert((InGame, PauseMenu));
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    controls: Res<PlayerControls>,
+) {
+    spawn_back_button(&mut commands, &font_assets);
+    spawn_controls_panel(&mut commands, &font_assets, &controls).insert((InGame, PauseMenu));
+}
+
+fn despawn_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    commands.remove_resource::<PendingRebind>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
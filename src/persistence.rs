@@ -0,0 +1,71 @@
+use bevy::log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+// Small key/value store for user preferences.
+// Native builds write one RON file per key in `config/`, the web build uses `localStorage`.
+
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_DIR: &str = "config";
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_PREFIX: &str = "tablet_takedown.";
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let content = read(key)?;
+    match ron::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("Ignoring saved {key}: {err}");
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(content) => write(key, &content),
+        Err(err) => warn!("Could not serialize {key}: {err}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(std::path::Path::new(CONFIG_DIR).join(format!("{key}.ron"))).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, content: &str) {
+    let path = std::path::Path::new(CONFIG_DIR).join(format!("{key}.ron"));
+    if let Err(err) =
+        std::fs::create_dir_all(CONFIG_DIR).and_then(|_| std::fs::write(&path, content))
+    {
+        warn!("Could not save {}: {err}", path.display());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("{STORAGE_PREFIX}{key}"))
+        .ok()
+        .flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, content: &str) {
+    let saved = local_storage()
+        .map(|storage| {
+            storage
+                .set_item(&format!("{STORAGE_PREFIX}{key}"), content)
+                .is_ok()
+        })
+        .unwrap_or(false);
+    if !saved {
+        warn!("Could not save {key} to local storage");
+    }
+}
@@ -0,0 +1,246 @@
+use avian2d::prelude::{
+    Collider, ColliderDensity, Collisions, ExternalForce, PhysicsSet, RigidBody,
+};
+use bevy::{
+    app::{FixedPostUpdate, FixedUpdate, Plugin},
+    math::{Vec2, Vec3},
+    prelude::{
+        in_state, Commands, Component, Entity, Event, EventWriter, IntoSystemConfigs, OnEnter,
+        Query, Res, ResMut, Resource, Transform, With,
+    },
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::arena_plugin::Arena,
+    game_tuning::game_tuning_plugin::GameTuning,
+    rollback::rollback_plugin::{RollbackApp, RollbackIds},
+    tablet::tablet_plugin::Tablet,
+    try_kill_bubbles, use_turbo,
+    water::water_plugin::{Water, WaterContact},
+    AppState, GameRng, Health, InGame, PauseState, Player, SimulationSet, Volume,
+};
+
+// distance kept from the walls when dropping an item
+const DROP_MARGIN: f32 = 100.;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickupKind {
+    /// Heals the tablet that grabs it.
+    SugarCube,
+    /// Boosts the thrusters of the tablet that grabs it for a while.
+    LemonSlice,
+    /// Heavy obstacle melting away, nobody can grab it.
+    IceCube,
+}
+
+impl PickupKind {
+    const ALL: [PickupKind; 3] = [
+        PickupKind::SugarCube,
+        PickupKind::LemonSlice,
+        PickupKind::IceCube,
+    ];
+
+    pub fn size(&self) -> Vec2 {
+        match self {
+            PickupKind::SugarCube => Vec2::splat(36.),
+            PickupKind::LemonSlice => Vec2::new(60., 16.),
+            PickupKind::IceCube => Vec2::splat(90.),
+        }
+    }
+
+    fn density(&self, tuning: &GameTuning) -> f32 {
+        match self {
+            PickupKind::IceCube => tuning.ice_density,
+            _ => tuning.cachet_density / 2.,
+        }
+    }
+}
+
+/// Item dropped into the arena, gone once grabbed or when `ticks_left` runs out.
+#[derive(Component, Clone)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub ticks_left: u32,
+    // whether it already fell into the water
+    pub landed: bool,
+}
+
+/// Thrust multiplier of a tablet that grabbed a lemon slice.
+#[derive(Component, Clone)]
+pub struct TurboBoost {
+    pub ticks_left: u32,
+}
+
+/// Sent when a tablet grabs an item, presentation plugins react to it.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct PickupEvent {
+    pub player: usize,
+    pub kind: PickupKind,
+}
+
+/// Sent when an ice cube falls into the water, presentation plugins react to it.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct IceCubeLanded;
+
+/// Ticks until the next item falls into the arena.
+#[derive(Resource, Clone, Default)]
+struct PickupDropper {
+    cooldown: u32,
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<PickupEvent>();
+        app.add_event::<IceCubeLanded>();
+        app.init_resource::<PickupDropper>();
+        app.rollback_resource::<PickupDropper>()
+            .rollback_component::<Pickup>()
+            .rollback_component::<TurboBoost>();
+        app.add_systems(OnEnter(AppState::InGame), reset_dropper);
+        app.add_systems(
+            FixedUpdate,
+            (drop_pickups, land_pickups, expire_pickups, expire_boosts)
+                .chain()
+                .before(use_turbo)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+        app.add_systems(
+            FixedPostUpdate,
+            collect_pickups
+                .after(PhysicsSet::Sync)
+                .before(try_kill_bubbles)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+fn reset_dropper(mut commands: Commands, tuning: Res<GameTuning>) {
+    commands.insert_resource(PickupDropper {
+        cooldown: tuning.pickup_interval,
+    });
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    mut game_rng: ResMut<GameRng>,
+    mut dropper: ResMut<PickupDropper>,
+    mut rollback_ids: ResMut<RollbackIds>,
+) {
+    dropper.cooldown = dropper.cooldown.saturating_sub(1);
+    if dropper.cooldown > 0 {
+        return;
+    }
+    dropper.cooldown = tuning.pickup_interval;
+
+    let rng = &mut game_rng.rng;
+    let kind = PickupKind::ALL[rng.random_range(0..PickupKind::ALL.len())];
+    let left = arena.water.left + DROP_MARGIN;
+    let right = (arena.water.right - DROP_MARGIN).max(left);
+    let x = rng.random_range(left..=right);
+    let size = kind.size();
+    commands.spawn((
+        InGame,
+        rollback_ids.next(),
+        Pickup {
+            kind,
+            ticks_left: tuning.pickup_lifetime,
+            landed: false,
+        },
+        RigidBody::Dynamic,
+        Collider::rectangle(size.x, size.y),
+        Transform::from_translation(Vec3::new(x, arena.bounds.max.y, 0.)),
+        ColliderDensity(kind.density(&tuning)),
+        Volume(size.x * size.y),
+        WaterContact::default(),
+        ExternalForce::default().with_persistence(false),
+    ));
+}
+
+fn land_pickups(
+    water: Res<Water>,
+    mut landed_events: EventWriter<IceCubeLanded>,
+    mut query: Query<(&mut Pickup, &Transform)>,
+) {
+    for (mut pickup, transform) in &mut query {
+        if !pickup.landed && water.contains(&transform.translation) {
+            pickup.landed = true;
+            if pickup.kind == PickupKind::IceCube {
+                landed_events.send(IceCubeLanded);
+            }
+        }
+    }
+}
+
+// ice melts away over its lifetime, the other items vanish at once
+fn expire_pickups(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    mut query: Query<(Entity, &mut Pickup, &mut Transform, &mut Volume)>,
+) {
+    for (entity, mut pickup, mut transform, mut volume) in &mut query {
+        pickup.ticks_left = pickup.ticks_left.saturating_sub(1);
+        if pickup.ticks_left == 0 {
+            commands.entity(entity).despawn();
+        } else if pickup.kind == PickupKind::IceCube {
+            let scale = pickup.ticks_left as f32 / tuning.pickup_lifetime.max(1) as f32;
+            let size = pickup.kind.size() * scale;
+            transform.scale = Vec3::new(scale, scale, 1.);
+            volume.0 = size.x * size.y;
+        }
+    }
+}
+
+fn expire_boosts(mut commands: Commands, mut query: Query<(Entity, &mut TurboBoost)>) {
+    for (entity, mut boost) in &mut query {
+        boost.ticks_left = boost.ticks_left.saturating_sub(1);
+        if boost.ticks_left == 0 {
+            commands.entity(entity).remove::<TurboBoost>();
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    tuning: Res<GameTuning>,
+    collisions: Res<Collisions>,
+    mut pickup_events: EventWriter<PickupEvent>,
+    pickups: Query<(Entity, &Pickup)>,
+    mut players: Query<(Entity, &Player, &Tablet, &mut Health), With<Player>>,
+) {
+    for (pickup_entity, pickup) in pickups.iter() {
+        if pickup.kind == PickupKind::IceCube {
+            continue;
+        }
+        let grabbed_by = players.iter_mut().find(|(player_entity, _, _, health)| {
+            health.0 > 0. && collisions.contains(*player_entity, pickup_entity)
+        });
+        let Some((player_entity, player, tablet, mut health)) = grabbed_by else {
+            continue;
+        };
+
+        match pickup.kind {
+            PickupKind::SugarCube => {
+                health.0 = (health.0 + tuning.sugar_heal).min(tablet.max_health);
+            }
+            PickupKind::LemonSlice => {
+                commands.entity(player_entity).insert(TurboBoost {
+                    ticks_left: tuning.lemon_duration,
+                });
+            }
+            PickupKind::IceCube => (),
+        }
+        pickup_events.send(PickupEvent {
+            player: player.0,
+            kind: pickup.kind,
+        });
+        commands.entity(pickup_entity).despawn();
+    }
+}
@@ -0,0 +1 @@
+pub mod picture_in_picture_plugin;
@@ -0,0 +1,164 @@
+use bevy::{
+    app::{Plugin, Update},
+    math::{Rect, UVec2, Vec2, Vec3Swizzles},
+    prelude::{
+        in_state, Camera, Camera2d, Commands, Component, DespawnRecursiveExt, DetectChangesMut,
+        Entity, IntoSystemConfigs, OrthographicProjection, Query, Res, Transform, With, Without,
+    },
+    render::camera::Viewport,
+    ui::{BorderColor, Node, PositionType, UiRect, Val},
+    utils::default,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    arena::arena_plugin::Arena, lobby::lobby_plugin::PlayerColors,
+    settings::settings_plugin::GameSettings, spectator::spectator_plugin::CameraView, AppState,
+    Health, InGame, Player,
+};
+
+// margin around the arena the main camera keeps framing, relative to its largest side
+const MAIN_AREA_MARGIN: f32 = 0.25;
+// side of a view relative to the window height
+const VIEW_SIZE: f32 = 0.22;
+const VIEW_MARGIN: f32 = 16.;
+// leaves room for the health bars
+const VIEW_TOP: f32 = 120.;
+const VIEW_SCALE: f32 = 2.;
+const FRAME_BORDER: f32 = 4.;
+
+/// Camera of the small view showing the tablet of a `Player` index.
+#[derive(Component)]
+pub struct PipCamera(pub usize);
+
+// coloured border around a view, drawn by the main camera
+#[derive(Component)]
+struct PipFrame(usize);
+
+/// Part of the arena the main camera keeps framing once picture in picture is on,
+/// the tablets that leave it get a view of their own.
+pub fn main_area(arena: &Arena) -> Rect {
+    arena
+        .bounds
+        .inflate(arena.bounds.size().max_element() * MAIN_AREA_MARGIN)
+}
+
+/// Optional small views, stacked on the right of the screen, for the tablets launched far from the glass.
+pub struct PictureInPicturePlugin;
+
+impl Plugin for PictureInPicturePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(Update, update_views.run_if(in_state(AppState::InGame)));
+    }
+}
+
+// only alongside the camera framing the whole match, following or free cameras go where they want
+fn update_views(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    view: Res<CameraView>,
+    arena: Res<Arena>,
+    player_colors: Res<PlayerColors>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    player_query: Query<(&Player, &Transform, &Health), Without<PipCamera>>,
+    mut camera_query: Query<(Entity, &PipCamera, &mut Camera, &mut Transform)>,
+    mut frame_query: Query<(Entity, &PipFrame, &mut Node)>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let area = main_area(&arena);
+    let mut far: Vec<(usize, Vec2)> = player_query
+        .iter()
+        .filter(|_| settings.picture_in_picture && *view == CameraView::Match)
+        .filter(|(_, transform, health)| {
+            health.0 > 0. && !area.contains(transform.translation.xy())
+        })
+        .map(|(player, transform, _)| (player.0, transform.translation.xy()))
+        .collect();
+    far.sort_by_key(|(player, _)| *player);
+
+    let scale_factor = window.scale_factor();
+    let size = (window.physical_height() as f32 * VIEW_SIZE) as u32;
+    let margin = (VIEW_MARGIN * scale_factor) as u32;
+    let top = (VIEW_TOP * scale_factor) as u32;
+    // as many as fit down the window
+    let fitting = window.physical_height().saturating_sub(top) / (size + margin).max(1);
+    far.truncate(fitting as usize);
+
+    for (entity, camera, _, _) in &camera_query {
+        if !far.iter().any(|(player, _)| *player == camera.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, frame, _) in &frame_query {
+        if !far.iter().any(|(player, _)| *player == frame.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for (slot, (player, position)) in far.into_iter().enumerate() {
+        let physical_position = UVec2::new(
+            window.physical_width().saturating_sub(size + margin),
+            top + slot as u32 * (size + margin),
+        );
+        let viewport = Viewport {
+            physical_position,
+            physical_size: UVec2::splat(size),
+            ..default()
+        };
+        let frame = Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(physical_position.x as f32 / scale_factor - FRAME_BORDER),
+            top: Val::Px(physical_position.y as f32 / scale_factor - FRAME_BORDER),
+            width: Val::Px(size as f32 / scale_factor + 2. * FRAME_BORDER),
+            height: Val::Px(size as f32 / scale_factor + 2. * FRAME_BORDER),
+            border: UiRect::all(Val::Px(FRAME_BORDER)),
+            ..default()
+        };
+
+        match camera_query
+            .iter_mut()
+            .find(|(_, camera, _, _)| camera.0 == player)
+        {
+            Some((_, _, mut camera, mut transform)) => {
+                camera.viewport = Some(viewport);
+                transform.translation = position.extend(0.);
+            }
+            None => {
+                commands.spawn((
+                    InGame,
+                    PipCamera(player),
+                    Camera2d,
+                    Camera {
+                        // drawn over the main camera, each in its own order
+                        order: 1 + player as isize,
+                        viewport: Some(viewport),
+                        ..default()
+                    },
+                    OrthographicProjection {
+                        scale: VIEW_SCALE,
+                        ..OrthographicProjection::default_2d()
+                    },
+                    Transform::from_translation(position.extend(0.)),
+                ));
+            }
+        }
+        match frame_query
+            .iter_mut()
+            .find(|(_, pip_frame, _)| pip_frame.0 == player)
+        {
+            Some((_, _, mut node)) => {
+                node.set_if_neq(frame);
+            }
+            None => {
+                commands.spawn((
+                    InGame,
+                    PipFrame(player),
+                    frame,
+                    BorderColor(player_colors.get(player)),
+                ));
+            }
+        }
+    }
+}
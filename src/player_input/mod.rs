@@ -0,0 +1 @@
+pub mod player_input_plugin;
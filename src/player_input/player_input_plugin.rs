@@ -0,0 +1,216 @@
+use bevy::{
+    app::{Plugin, PreUpdate},
+    input::{
+        gamepad::{Gamepad, GamepadButton, GamepadConnectionEvent},
+        ButtonInput, InputSystem,
+    },
+    prelude::{
+        in_state, Entity, EventReader, IntoSystemConfigs, KeyCode, Query, Res, ResMut, Resource,
+        Without,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::bot_plugin::Bot, persistence, AppState, Player, PlayerIntent, PlayerKeyMap, MAX_PLAYERS,
+    PLAYER_CONTROL,
+};
+
+const CONTROLS_KEY: &str = "controls";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Thruster {
+    Up,
+    Left,
+    Right,
+    Down,
+}
+
+impl Thruster {
+    pub const ALL: [Thruster; 4] = [
+        Thruster::Up,
+        Thruster::Left,
+        Thruster::Right,
+        Thruster::Down,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Thruster::Up => "Up",
+            Thruster::Left => "Left",
+            Thruster::Right => "Right",
+            Thruster::Down => "Down",
+        }
+    }
+}
+
+impl PlayerKeyMap {
+    pub fn key(&self, thruster: Thruster) -> KeyCode {
+        match thruster {
+            Thruster::Up => self.up,
+            Thruster::Left => self.left,
+            Thruster::Right => self.right,
+            Thruster::Down => self.down,
+        }
+    }
+
+    pub fn set_key(&mut self, thruster: Thruster, key: KeyCode) {
+        match thruster {
+            Thruster::Up => self.up = key,
+            Thruster::Left => self.left = key,
+            Thruster::Right => self.right = key,
+            Thruster::Down => self.down = key,
+        }
+    }
+}
+
+/// Keyboard layout of every player, editable from the controls menu.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct PlayerControls(pub [PlayerKeyMap; MAX_PLAYERS]);
+
+impl Default for PlayerControls {
+    fn default() -> Self {
+        PlayerControls(PLAYER_CONTROL)
+    }
+}
+
+impl PlayerControls {
+    pub fn load() -> Self {
+        persistence::load(CONTROLS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        persistence::save(CONTROLS_KEY, self);
+    }
+
+    /// Returns the player and thruster already using `key`, if any.
+    pub fn find(&self, key: KeyCode) -> Option<(usize, Thruster)> {
+        self.0.iter().enumerate().find_map(|(player, key_map)| {
+            Thruster::ALL
+                .into_iter()
+                .find(|thruster| key_map.key(*thruster) == key)
+                .map(|thruster| (player, thruster))
+        })
+    }
+}
+
+/// What a human player joined the lobby with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputDevice {
+    /// Index of the key map in `PlayerControls`.
+    Keyboard(usize),
+    Gamepad(Entity),
+}
+
+/// Device of each `Player` index, players without one use the key map and gamepad of their index.
+#[derive(Resource, Default)]
+pub struct PlayerDevices(pub Vec<Option<InputDevice>>);
+
+/// Gamepads in the order they first connected, a reconnected gamepad keeps its place.
+/// Players without a device get the gamepad at their index.
+#[derive(Resource, Default)]
+struct GamepadOrder(Vec<Entity>);
+
+pub fn key_label(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(PlayerControls::load());
+        app.init_resource::<PlayerDevices>();
+        app.init_resource::<GamepadOrder>();
+        app.add_systems(
+            PreUpdate,
+            (
+                order_gamepads,
+                update_player_intent.run_if(in_state(AppState::InGame)),
+            )
+                .chain()
+                .after(InputSystem),
+        );
+    }
+}
+
+fn keyboard_intent(keyboard_input: &ButtonInput<KeyCode>, key_map: &PlayerKeyMap) -> PlayerIntent {
+    let pressed = |thruster| {
+        if keyboard_input.pressed(key_map.key(thruster)) {
+            1.
+        } else {
+            0.
+        }
+    };
+    PlayerIntent {
+        up: pressed(Thruster::Up),
+        left: pressed(Thruster::Left),
+        right: pressed(Thruster::Right),
+        down: pressed(Thruster::Down),
+    }
+}
+
+// sticks and triggers are analog, so a half pulled trigger gives half the turbo force
+fn gamepad_intent(gamepad: &Gamepad) -> PlayerIntent {
+    let stick = gamepad.left_stick();
+    let analog = |button| gamepad.get(button).unwrap_or(0.);
+    PlayerIntent {
+        up: analog(GamepadButton::South).max(stick.y),
+        left: analog(GamepadButton::LeftTrigger2)
+            .max(analog(GamepadButton::LeftTrigger))
+            .max(-stick.x),
+        right: analog(GamepadButton::RightTrigger2)
+            .max(analog(GamepadButton::RightTrigger))
+            .max(stick.x),
+        down: analog(GamepadButton::East).max(-stick.y),
+    }
+}
+
+// a gamepad entity outlives its disconnection, so it is found again when it reconnects
+fn order_gamepads(
+    mut order: ResMut<GamepadOrder>,
+    mut connections: EventReader<GamepadConnectionEvent>,
+) {
+    for connection in connections.read() {
+        if connection.connected() && !order.0.contains(&connection.gamepad) {
+            order.0.push(connection.gamepad);
+        }
+    }
+}
+
+fn update_player_intent(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls: Res<PlayerControls>,
+    devices: Res<PlayerDevices>,
+    gamepad_order: Res<GamepadOrder>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    mut query: Query<(&Player, &mut PlayerIntent), Without<Bot>>,
+) {
+    for (player, mut intent) in &mut query {
+        *intent = match devices.0.get(player.0).copied().flatten() {
+            Some(InputDevice::Keyboard(key_map)) => {
+                keyboard_intent(&keyboard_input, &controls.0[key_map])
+            }
+            Some(InputDevice::Gamepad(entity)) => gamepads
+                .get(entity)
+                .map(|(_, gamepad)| gamepad_intent(gamepad))
+                .unwrap_or_default(),
+            None => {
+                let mut new_intent = keyboard_intent(&keyboard_input, &controls.0[player.0]);
+                // a disconnected gamepad leaves its player without one until it is back
+                let gamepad = gamepad_order
+                    .0
+                    .get(player.0)
+                    .and_then(|entity| gamepads.get(*entity).ok());
+                if let Some((_, gamepad)) = gamepad {
+                    new_intent = new_intent.max(gamepad_intent(gamepad));
+                }
+                new_intent
+            }
+        };
+    }
+}
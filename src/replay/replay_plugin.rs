@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use bevy::{
+    app::{FixedUpdate, Plugin, Update},
+    asset::Assets,
+    color::Color,
+    input::ButtonInput,
+    prelude::{
+        in_state, not, resource_exists, Commands, Component, Condition, IntoSystemConfigs, KeyCode,
+        NextState, OnEnter, OnExit, Query, Res, ResMut, Resource, Text, With,
+    },
+    text::{TextColor, TextFont},
+    time::{Fixed, Time, Virtual},
+    ui::{Node, PositionType, Val},
+    utils::default,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::arena_plugin::Arena,
+    bot::bot_plugin::BotPlayers,
+    game_match::game_match_plugin::MatchScore,
+    game_tuning::game_tuning_plugin::{GameTuning, TuningAssets},
+    hazard::hazard_plugin::Hazards,
+    persistence, seed_round,
+    tablet::tablet_plugin::PlayerTablets,
+    use_turbo, AppState, FontAssets, GameRng, InGame, MainMenuState, NetSession, PauseState,
+    Player, PlayerIntent, PlayerNumber, RoundOutcome, SimulationSet, Teams,
+};
+
+const REPLAY_KEY: &str = "last_replay";
+const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1., 2., 4.];
+const NORMAL_SPEED: usize = 2;
+const SEEK_SPEED: f32 = 16.;
+const SCRUB_SECONDS: f32 = 5.;
+const DEFAULT_MAX_DELTA: Duration = Duration::from_millis(250);
+
+/// Everything needed to play a round again: its seed, its tuning, its arena, its hazards, the type and team of every tablet and their intent at each tick.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RoundReplay {
+    pub player_number: usize,
+    pub seed: u64,
+    pub tuning: GameTuning,
+    #[serde(default)]
+    pub arena: Arena,
+    #[serde(default)]
+    pub hazards: Hazards,
+    #[serde(default)]
+    pub tablets: PlayerTablets,
+    #[serde(default)]
+    pub teams: Teams,
+    pub ticks: Vec<Vec<PlayerIntent>>,
+}
+
+impl RoundReplay {
+    // resources the round is set up from, inserted before it is entered
+    fn insert_setup(&self, commands: &mut Commands) {
+        commands.insert_resource(PlayerNumber(self.player_number));
+        commands.insert_resource(self.tuning.clone());
+        commands.insert_resource(self.arena.clone());
+        commands.insert_resource(self.hazards);
+        commands.insert_resource(self.tablets.clone());
+        commands.insert_resource(self.teams.clone());
+    }
+}
+
+/// Every round of a match in the order they were played, with the scoreboard between them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub rounds_to_win: u32,
+    pub rounds: Vec<RoundReplay>,
+}
+
+impl Replay {
+    pub fn load() -> Option<Self> {
+        persistence::load(REPLAY_KEY)
+    }
+
+    pub fn save(&self) {
+        persistence::save(REPLAY_KEY, self);
+    }
+}
+
+// match being recorded, saved as the last replay each time one of its rounds is decided
+#[derive(Resource, Default)]
+struct ReplayRecorder {
+    replay: Option<Replay>,
+    round: Option<RoundReplay>,
+}
+
+/// Present while a replay is watched instead of played.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    round: usize,
+    // score before the round being watched, scrubbing back plays it again from there
+    round_score: Option<MatchScore>,
+    tick: usize,
+    speed: usize,
+    paused: bool,
+    seek_to: Option<usize>,
+    live_tuning: GameTuning,
+}
+
+#[derive(Component)]
+struct ReplayOverlay;
+
+/// Sets up a match that plays `replay` back, the caller then enters `AppState::InGame`.
+/// Does nothing and returns `false` for a replay without any round.
+pub fn start_playback(commands: &mut Commands, replay: Replay, live_tuning: &GameTuning) -> bool {
+    let Some(first_round) = replay.rounds.first() else {
+        return false;
+    };
+    first_round.insert_setup(commands);
+    commands.insert_resource(BotPlayers::default());
+    commands.insert_resource(MatchScore {
+        wins: vec![0; first_round.teams.side_count(first_round.player_number)],
+        rounds_to_win: replay.rounds_to_win,
+    });
+    commands.insert_resource(ReplayPlayback {
+        replay,
+        round: 0,
+        round_score: None,
+        tick: 0,
+        speed: NORMAL_SPEED,
+        paused: false,
+        seek_to: None,
+        live_tuning: live_tuning.clone(),
+    });
+    true
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<ReplayRecorder>();
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (start_round.after(seed_round), spawn_overlay),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                feed_intents.run_if(resource_exists::<ReplayPlayback>),
+                // a LAN round plays some ticks again after a rollback
+                record_intents.run_if(
+                    not(resource_exists::<ReplayPlayback>).and(not(resource_exists::<NetSession>)),
+                ),
+            )
+                .before(use_turbo)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+        app.add_systems(
+            Update,
+            (playback_controls, update_overlay)
+                .chain()
+                .run_if(in_state(PauseState::Running))
+                .run_if(resource_exists::<ReplayPlayback>),
+        );
+        app.add_systems(OnExit(AppState::InGame), save_recording);
+        app.add_systems(
+            OnEnter(AppState::Scoreboard),
+            next_playback_round.run_if(resource_exists::<ReplayPlayback>),
+        );
+        app.add_systems(OnEnter(AppState::MainMenu), (end_recording, end_playback));
+    }
+}
+
+fn start_round(
+    player_number: Res<PlayerNumber>,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    hazards: Res<Hazards>,
+    tablets: Res<PlayerTablets>,
+    teams: Res<Teams>,
+    mut match_score: ResMut<MatchScore>,
+    mut game_rng: ResMut<GameRng>,
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Option<ResMut<ReplayPlayback>>,
+) {
+    if let Some(mut playback) = playback {
+        let seed = playback.replay.rounds[playback.round].seed;
+        *game_rng = GameRng::from_seed(seed);
+        playback.tick = 0;
+        // the same round entered again was scrubbed back, its win must not count twice
+        if let Some(round_score) = playback.round_score.clone() {
+            *match_score = round_score;
+        } else {
+            playback.round_score = Some(match_score.clone());
+        }
+        recorder.round = None;
+    } else {
+        if recorder.replay.is_none() {
+            recorder.replay = Some(Replay {
+                rounds_to_win: match_score.rounds_to_win,
+                rounds: Vec::new(),
+            });
+        }
+        recorder.round = Some(RoundReplay {
+            player_number: player_number.0,
+            seed: game_rng.seed,
+            tuning: tuning.clone(),
+            arena: arena.clone(),
+            hazards: *hazards,
+            tablets: tablets.clone(),
+            teams: teams.clone(),
+            ticks: Vec::new(),
+        });
+    }
+}
+
+fn record_intents(mut recorder: ResMut<ReplayRecorder>, query: Query<(&Player, &PlayerIntent)>) {
+    let Some(round) = recorder.round.as_mut() else {
+        return;
+    };
+    let mut intents = vec![PlayerIntent::default(); round.player_number];
+    for (player, intent) in query.iter() {
+        if let Some(recorded) = intents.get_mut(player.0) {
+            *recorded = *intent;
+        }
+    }
+    round.ticks.push(intents);
+}
+
+// overrides whatever keyboard, gamepads or bots asked for this tick
+fn feed_intents(
+    mut playback: ResMut<ReplayPlayback>,
+    mut query: Query<(&Player, &mut PlayerIntent)>,
+) {
+    let tick = playback.tick;
+    let intents = playback.replay.rounds[playback.round].ticks.get(tick);
+    for (player, mut intent) in query.iter_mut() {
+        *intent = intents
+            .and_then(|intents| intents.get(player.0))
+            .copied()
+            .unwrap_or_default();
+    }
+    playback.tick += 1;
+}
+
+// a round left before it was decided, restarted or quit, is not part of the match
+fn save_recording(mut recorder: ResMut<ReplayRecorder>, round_outcome: Option<Res<RoundOutcome>>) {
+    let Some(round) = recorder.round.take() else {
+        return;
+    };
+    if round_outcome.is_none() || round.ticks.is_empty() {
+        return;
+    }
+    if let Some(replay) = recorder.replay.as_mut() {
+        replay.rounds.push(round);
+        replay.save();
+    }
+}
+
+fn end_recording(mut recorder: ResMut<ReplayRecorder>) {
+    *recorder = ReplayRecorder::default();
+}
+
+// Space pauses, Up/Down change the speed and Left/Right scrub, Escape opens the pause menu as in a match.
+// Scrubbing back restarts the round and fast-forwards to the wanted tick.
+fn playback_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let scrub_ticks = (SCRUB_SECONDS / fixed_time.timestep().as_secs_f32()) as usize;
+    let length = playback.replay.rounds[playback.round].ticks.len();
+
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        playback.speed = (playback.speed + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        playback.speed = playback.speed.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        let from = playback.seek_to.unwrap_or(playback.tick);
+        playback.seek_to = Some((from + scrub_ticks).min(length));
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        let from = playback.seek_to.unwrap_or(playback.tick);
+        playback.seek_to = Some(from.saturating_sub(scrub_ticks));
+        app_state.set(AppState::RestartRound);
+    }
+
+    if let Some(target) = playback.seek_to {
+        if playback.tick >= target {
+            playback.seek_to = None;
+        }
+    }
+
+    if playback.seek_to.is_some() {
+        virtual_time.set_max_delta(Duration::from_secs(1));
+        virtual_time.set_relative_speed(SEEK_SPEED);
+        virtual_time.unpause();
+    } else {
+        virtual_time.set_max_delta(DEFAULT_MAX_DELTA);
+        virtual_time.set_relative_speed(PLAYBACK_SPEEDS[playback.speed]);
+        if playback.paused {
+            virtual_time.pause();
+        } else {
+            virtual_time.unpause();
+        }
+    }
+}
+
+fn spawn_overlay(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if playback.is_none() {
+        return;
+    }
+    commands.spawn((
+        InGame,
+        ReplayOverlay,
+        Text::default(),
+        TextFont {
+            font: font_assets.bold.clone(),
+            font_size: 28.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.),
+            left: Val::Px(20.),
+            ..default()
+        },
+    ));
+}
+
+fn update_overlay(
+    fixed_time: Res<Time<Fixed>>,
+    playback: Res<ReplayPlayback>,
+    mut query: Query<&mut Text, With<ReplayOverlay>>,
+) {
+    let step = fixed_time.timestep().as_secs_f32();
+    let state = if playback.seek_to.is_some() {
+        "seeking".to_string()
+    } else if playback.paused {
+        "paused".to_string()
+    } else {
+        format!("x{}", PLAYBACK_SPEEDS[playback.speed])
+    };
+    for mut text in query.iter_mut() {
+        text.0 = format!(
+            "Replay  round {}/{}  {:.1}s / {:.1}s  {}\n[Space] pause  [Up/Down] speed  [Left/Right] scrub  [Esc] menu",
+            playback.round + 1,
+            playback.replay.rounds.len(),
+            playback.tick as f32 * step,
+            playback.replay.rounds[playback.round].ticks.len() as f32 * step,
+            state
+        );
+    }
+}
+
+// the scoreboard leads to the next recorded round, or back to the menu after the last one
+// of a match that was quit before it was won
+fn next_playback_round(
+    mut commands: Commands,
+    match_score: Res<MatchScore>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MainMenuState>>,
+) {
+    let next = playback.round + 1;
+    if let Some(round) = playback.replay.rounds.get(next) {
+        round.insert_setup(&mut commands);
+        playback.round = next;
+        playback.round_score = None;
+    } else if match_score.match_winner().is_none() {
+        app_state.set(AppState::MainMenu);
+        menu_state.set(MainMenuState::HomeMenu);
+    }
+}
+
+fn end_playback(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    tuning_assets: Option<Res<TuningAssets>>,
+    tuning_files: Res<Assets<GameTuning>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    let Some(playback) = playback else {
+        return;
+    };
+    // the tuning file may have been edited while watching
+    let live_tuning = tuning_assets
+        .and_then(|assets| tuning_files.get(assets.game.id()).cloned())
+        .unwrap_or_else(|| playback.live_tuning.clone());
+    commands.insert_resource(live_tuning);
+    commands.remove_resource::<ReplayPlayback>();
+
+    virtual_time.set_max_delta(DEFAULT_MAX_DELTA);
+    virtual_time.set_relative_speed(1.);
+    virtual_time.unpause();
+}
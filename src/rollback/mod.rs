@@ -0,0 +1 @@
+pub mod rollback_plugin;
@@ -0,0 +1,208 @@
+use avian2d::prelude::{
+    AngularVelocity, Collider, ColliderDensity, Collisions, ExternalForce, LinearVelocity,
+    Position, RigidBody, Rotation, Sleeping, TimeSleeping,
+};
+use bevy::{
+    app::{App, Plugin},
+    ecs::entity::{EntityHashMap, EntityMapper, MapEntities},
+    prelude::{Component, DespawnRecursiveExt, Entity, Resource, Transform, World},
+    utils::HashMap,
+};
+
+/// Identity of a simulated entity, the same in every world that played the same ticks.
+/// Spawn sites take it from `RollbackIds` so that a snapshot can be matched entity by entity.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rollback(u32);
+
+impl Rollback {
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Hands out `Rollback` ids in spawn order, reset between rounds.
+#[derive(Resource, Clone, Default)]
+pub struct RollbackIds {
+    next: u32,
+}
+
+impl RollbackIds {
+    pub fn next(&mut self) -> Rollback {
+        self.next += 1;
+        Rollback(self.next)
+    }
+}
+
+// source entity of each restored entity and the other way around
+struct RollbackMap {
+    pairs: Vec<(Entity, Entity)>,
+    map: EntityHashMap<Entity>,
+}
+
+impl EntityMapper for &RollbackMap {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.map
+            .get(&entity)
+            .copied()
+            .unwrap_or(Entity::PLACEHOLDER)
+    }
+}
+
+type CopyState = fn(&World, &mut World, &RollbackMap);
+
+/// Components and resources that make up the state of a round, copied by `restore`.
+#[derive(Resource, Default)]
+struct RollbackRegistry(Vec<CopyState>);
+
+/// Registration of the state a plugin adds to the simulation.
+pub trait RollbackApp {
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self;
+    fn rollback_resource<R: Resource + Clone>(&mut self) -> &mut Self;
+    /// For resources pointing at simulated entities, which get other ids once restored.
+    fn rollback_resource_with_entities<R: Resource + Clone + MapEntities>(&mut self) -> &mut Self;
+}
+
+impl RollbackApp for App {
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self {
+        register(self, copy_component::<T>)
+    }
+
+    fn rollback_resource<R: Resource + Clone>(&mut self) -> &mut Self {
+        register(self, copy_resource::<R>)
+    }
+
+    fn rollback_resource_with_entities<R: Resource + Clone + MapEntities>(&mut self) -> &mut Self {
+        register(self, copy_resource_with_entities::<R>)
+    }
+}
+
+fn register(app: &mut App, copy: CopyState) -> &mut App {
+    app.world_mut()
+        .get_resource_or_init::<RollbackRegistry>()
+        .0
+        .push(copy);
+    app
+}
+
+fn copy_component<T: Component + Clone>(source: &World, target: &mut World, map: &RollbackMap) {
+    for &(from, to) in &map.pairs {
+        match source.get::<T>(from) {
+            Some(component) => {
+                target.entity_mut(to).insert(component.clone());
+            }
+            None => {
+                target.entity_mut(to).remove::<T>();
+            }
+        }
+    }
+}
+
+fn copy_resource<R: Resource + Clone>(source: &World, target: &mut World, _: &RollbackMap) {
+    match source.get_resource::<R>() {
+        Some(resource) => target.insert_resource(resource.clone()),
+        None => {
+            target.remove_resource::<R>();
+        }
+    }
+}
+
+fn copy_resource_with_entities<R: Resource + Clone + MapEntities>(
+    source: &World,
+    target: &mut World,
+    mut map: &RollbackMap,
+) {
+    match source.get_resource::<R>() {
+        Some(resource) => {
+            let mut resource = resource.clone();
+            resource.map_entities(&mut map);
+            target.insert_resource(resource);
+        }
+        None => {
+            target.remove_resource::<R>();
+        }
+    }
+}
+
+// contacts are kept so that the solver warm starts and `collision_started` holds as in the source
+fn copy_collisions(source: &World, target: &mut World, map: &RollbackMap) {
+    let mut collisions = Collisions::default();
+    for contacts in source.resource::<Collisions>().get_internal().values() {
+        let (Some(&entity1), Some(&entity2)) = (
+            map.map.get(&contacts.entity1),
+            map.map.get(&contacts.entity2),
+        ) else {
+            continue;
+        };
+        let mut contacts = contacts.clone();
+        contacts.entity1 = entity1;
+        contacts.entity2 = entity2;
+        contacts.body_entity1 = contacts
+            .body_entity1
+            .and_then(|entity| map.map.get(&entity).copied());
+        contacts.body_entity2 = contacts
+            .body_entity2
+            .and_then(|entity| map.map.get(&entity).copied());
+        collisions.insert_collision_pair(contacts);
+    }
+    target.insert_resource(collisions);
+}
+
+/// Brings `target` to the state of the round simulated in `source`.
+/// Entities are matched by `Rollback` id so that rendering and audio attached in `target` survive,
+/// the ones missing from `source` are despawned and the new ones spawned.
+pub fn restore(source: &mut World, target: &mut World) {
+    let mut source_query = source.query::<(Entity, &Rollback)>();
+    let mut sources: Vec<(Entity, Rollback)> = source_query
+        .iter(source)
+        .map(|(entity, id)| (entity, *id))
+        .collect();
+    sources.sort_by_key(|(_, id)| id.0);
+
+    let mut target_query = target.query::<(Entity, &Rollback)>();
+    let mut existing: HashMap<Rollback, Entity> = target_query
+        .iter(target)
+        .map(|(entity, id)| (*id, entity))
+        .collect();
+
+    let mut map = RollbackMap {
+        pairs: Vec::with_capacity(sources.len()),
+        map: EntityHashMap::default(),
+    };
+    for (from, id) in sources {
+        let to = existing
+            .remove(&id)
+            .unwrap_or_else(|| target.spawn(id).id());
+        map.pairs.push((from, to));
+        map.map.insert(from, to);
+    }
+    for entity in existing.into_values() {
+        target.entity_mut(entity).despawn_recursive();
+    }
+
+    let copies = target.resource::<RollbackRegistry>().0.clone();
+    for copy in copies {
+        copy(source, target, &map);
+    }
+}
+
+/// Tracks the physics state every simulated entity carries, the plugins register their own.
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackIds>();
+        app.rollback_resource::<RollbackIds>()
+            .rollback_component::<Transform>()
+            .rollback_component::<RigidBody>()
+            .rollback_component::<Collider>()
+            .rollback_component::<ColliderDensity>()
+            .rollback_component::<Position>()
+            .rollback_component::<Rotation>()
+            .rollback_component::<LinearVelocity>()
+            .rollback_component::<AngularVelocity>()
+            .rollback_component::<ExternalForce>()
+            .rollback_component::<Sleeping>()
+            .rollback_component::<TimeSleeping>();
+        register(app, copy_collisions);
+    }
+}
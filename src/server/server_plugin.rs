@@ -0,0 +1,454 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::{
+    app::{App, FixedPostUpdate, FixedUpdate, Plugin, Startup, Update},
+    log::{error, info, warn},
+    math::{EulerRot, Vec2},
+    prelude::{
+        in_state, resource_exists, Commands, EventReader, IntoSystemConfigs, NextState, OnEnter,
+        OnExit, Query, Res, ResMut, Resource, Transform,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::arena_plugin::Arena,
+    end_game_condition,
+    game_match::game_match_plugin::MatchScore,
+    game_tuning::game_tuning_plugin::GameTuning,
+    pickup::pickup_plugin::{Pickup, PickupKind},
+    rollback::rollback_plugin::Rollback,
+    simulation_app,
+    tablet::tablet_plugin::PlayerTablets,
+    use_turbo,
+    water::water_plugin::Water,
+    AppState, Health, PauseState, Player, PlayerEliminated, PlayerIntent, PlayerNumber,
+    RoundOutcome, SimulationSet, Teams, MAX_PLAYERS, MENU_DURATION,
+};
+
+// ticks between a round being decided and the next one, while the clients show its end and the scoreboard
+const ROUND_BREAK: u32 = 2 * MENU_DURATION;
+const DEFAULT_ADDRESS: &str = "0.0.0.0:7400";
+const DEFAULT_PLAYER_NUMBER: usize = 2;
+const DEFAULT_BEST_OF: u32 = 3;
+pub(crate) const MAX_PACKET_SIZE: usize = 64 * 1024;
+
+/// Address a `tablet-server` listens on and the match it hosts.
+#[derive(Resource, Clone, Debug)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    pub player_number: usize,
+    pub best_of: u32,
+}
+
+impl ServerConfig {
+    /// Reads `[address] [number of players] [best of]` from the command line, `0.0.0.0:7400 2 3` when left out.
+    pub fn from_args(args: &[String]) -> Option<ServerConfig> {
+        let mut values = args.iter().skip(1);
+        let address = values
+            .next()
+            .map_or(DEFAULT_ADDRESS, String::as_str)
+            .parse()
+            .ok()?;
+        let player_number = values
+            .next()
+            .map_or(Ok(DEFAULT_PLAYER_NUMBER), |value| value.parse())
+            .ok()?;
+        let best_of = values
+            .next()
+            .map_or(Ok(DEFAULT_BEST_OF), |value| value.parse())
+            .ok()?;
+        ((1..=MAX_PLAYERS).contains(&player_number) && best_of > 0).then_some(ServerConfig {
+            address,
+            player_number,
+            best_of,
+        })
+    }
+}
+
+/// What a thin client sends to the server, as RON over UDP.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum ClientMessage {
+    /// Sent until the server answers with `ServerMessage::Welcome`.
+    Join,
+    /// Sent by a client that only watches, until the server answers with `ServerMessage::Welcome`.
+    Spectate,
+    /// What the player asks their tablet to do, sent every frame.
+    Intent(PlayerIntent),
+}
+
+/// What the server sends to its thin clients, as RON over UDP.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ServerMessage {
+    /// Answers each `ClientMessage::Join` with the `Player` index given to that client,
+    /// and each `ClientMessage::Spectate` with none.
+    Welcome {
+        player: Option<usize>,
+        setup: Box<MatchSetup>,
+    },
+    /// Sent after every tick of a round.
+    Snapshot(Snapshot),
+}
+
+/// Everything a client needs to set up the rounds the server plays.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MatchSetup {
+    pub player_number: usize,
+    pub best_of: u32,
+    pub tuning: GameTuning,
+    pub arena: Arena,
+    pub tablets: PlayerTablets,
+    pub teams: Teams,
+}
+
+/// Authoritative state of a round after one of its ticks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub round: u32,
+    pub tick: u32,
+    pub tablets: Vec<TabletState>,
+    pub pickups: Vec<PickupState>,
+    pub water_level: f32,
+    pub outcome: Option<RoundOutcome>,
+    pub wins: Vec<u32>,
+    /// Every elimination of the round so far, so that a lost snapshot loses none.
+    pub eliminations: Vec<PlayerEliminated>,
+}
+
+/// Where a tablet is and how much of it is left.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TabletState {
+    pub player: usize,
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: f32,
+    pub health: f32,
+}
+
+impl TabletState {
+    /// State `t` of the way from `self` to `other`, turning the short way round.
+    pub fn lerp(&self, other: &TabletState, t: f32) -> TabletState {
+        TabletState {
+            player: self.player,
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation + short_turn(self.rotation, other.rotation) * t,
+            scale: self.scale + (other.scale - self.scale) * t,
+            health: self.health + (other.health - self.health) * t,
+        }
+    }
+}
+
+/// Where an item dropped into the arena is, `id` tells it apart from the other items of the round.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PickupState {
+    pub id: u32,
+    pub kind: PickupKind,
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl PickupState {
+    /// State `t` of the way from `self` to `other`, turning the short way round.
+    pub fn lerp(&self, other: &PickupState, t: f32) -> PickupState {
+        PickupState {
+            id: self.id,
+            kind: self.kind,
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation + short_turn(self.rotation, other.rotation) * t,
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+}
+
+// angle from `from` to `to` the short way round
+fn short_turn(from: f32, to: f32) -> f32 {
+    (to - from + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
+
+#[derive(Resource)]
+struct Server {
+    socket: UdpSocket,
+    // address of each `Player` index, in the order they joined
+    clients: Vec<SocketAddr>,
+    spectators: Vec<SocketAddr>,
+    // last intent received from each player, kept until a newer one arrives
+    intents: Vec<PlayerIntent>,
+    round: u32,
+    tick: u32,
+    round_break: u32,
+    eliminations: Vec<PlayerEliminated>,
+}
+
+impl Server {
+    fn send(&self, message: &ServerMessage, address: SocketAddr) {
+        let Ok(text) = ron::to_string(message) else {
+            return;
+        };
+        if let Err(err) = self.socket.send_to(text.as_bytes(), address) {
+            if err.kind() != ErrorKind::WouldBlock {
+                warn!("Could not send to {address}: {err}");
+            }
+        }
+    }
+}
+
+/// Plays the match given by a `ServerConfig` for thin clients: the rounds start once every
+/// player joined, each tablet follows the intents of its client and every tick is sent back to all of them.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, open_server);
+        app.add_systems(
+            Update,
+            (
+                receive_messages,
+                start_match.run_if(in_state(AppState::MainMenu)),
+            )
+                .chain()
+                .run_if(resource_exists::<Server>),
+        );
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_round.run_if(resource_exists::<Server>),
+        );
+        app.add_systems(
+            OnExit(AppState::InGame),
+            end_round.run_if(resource_exists::<Server>),
+        );
+        app.add_systems(
+            FixedUpdate,
+            apply_intents
+                .before(use_turbo)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running))
+                .run_if(resource_exists::<Server>),
+        );
+        app.add_systems(
+            FixedPostUpdate,
+            (
+                record_eliminations,
+                broadcast_snapshot,
+                next_round.run_if(resource_exists::<RoundOutcome>),
+            )
+                .chain()
+                .after(end_game_condition)
+                .run_if(in_state(PauseState::Running))
+                .run_if(resource_exists::<Server>),
+        );
+    }
+}
+
+/// Builds the headless app of a `tablet-server`, waiting in the main menu for its players to join.
+pub fn server_app(config: ServerConfig) -> App {
+    let mut app = simulation_app();
+    app.add_plugins(ServerPlugin);
+    app.insert_resource(config);
+    app.world_mut()
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::MainMenu);
+    app
+}
+
+fn open_server(mut commands: Commands, config: Res<ServerConfig>) {
+    let socket = match UdpSocket::bind(config.address) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("Could not listen on {}: {err}", config.address);
+            return;
+        }
+    };
+    if let Err(err) = socket.set_nonblocking(true) {
+        error!("Could not listen on {}: {err}", config.address);
+        return;
+    }
+    info!(
+        "Waiting for {} players on {}",
+        config.player_number, config.address
+    );
+    commands.insert_resource(Server {
+        socket,
+        clients: Vec::new(),
+        spectators: Vec::new(),
+        intents: vec![PlayerIntent::default(); config.player_number],
+        round: 0,
+        tick: 0,
+        round_break: ROUND_BREAK,
+        eliminations: Vec::new(),
+    });
+}
+
+// a client joining again, its welcome got lost, gets the same `Player` index
+fn receive_messages(
+    mut server: ResMut<Server>,
+    config: Res<ServerConfig>,
+    tuning: Res<GameTuning>,
+    arena: Res<Arena>,
+    tablets: Res<PlayerTablets>,
+    teams: Res<Teams>,
+) {
+    let mut buffer = vec![0; MAX_PACKET_SIZE];
+    loop {
+        let (size, address) = match server.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            // a client that left makes some platforms report an error
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+            Err(err) => {
+                warn!("Could not receive from the players: {err}");
+                break;
+            }
+        };
+        let message = std::str::from_utf8(&buffer[..size])
+            .ok()
+            .and_then(|text| ron::from_str::<ClientMessage>(text).ok());
+        let known = server.clients.iter().position(|client| *client == address);
+        let player = match message {
+            Some(ClientMessage::Join) => match known {
+                Some(player) => Some(player),
+                None if server.clients.len() < config.player_number => {
+                    server.clients.push(address);
+                    info!("Player {} joined from {address}", server.clients.len());
+                    Some(server.clients.len() - 1)
+                }
+                None => continue,
+            },
+            Some(ClientMessage::Spectate) => {
+                if !server.spectators.contains(&address) {
+                    server.spectators.push(address);
+                    info!("A spectator joined from {address}");
+                }
+                None
+            }
+            Some(ClientMessage::Intent(intent)) => {
+                if let Some(player) = known {
+                    server.intents[player] = intent;
+                }
+                continue;
+            }
+            None => continue,
+        };
+        let welcome = ServerMessage::Welcome {
+            player,
+            setup: Box::new(MatchSetup {
+                player_number: config.player_number,
+                best_of: config.best_of,
+                tuning: tuning.clone(),
+                arena: arena.clone(),
+                tablets: tablets.clone(),
+                teams: teams.clone(),
+            }),
+        };
+        server.send(&welcome, address);
+    }
+}
+
+fn start_match(
+    mut commands: Commands,
+    server: Res<Server>,
+    config: Res<ServerConfig>,
+    teams: Res<Teams>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if server.clients.len() < config.player_number {
+        return;
+    }
+    commands.insert_resource(PlayerNumber(config.player_number));
+    commands.insert_resource(MatchScore::new(
+        teams.side_count(config.player_number),
+        config.best_of,
+    ));
+    app_state.set(AppState::InGame);
+}
+
+fn start_round(mut server: ResMut<Server>) {
+    server.tick = 0;
+    server.round_break = ROUND_BREAK;
+    server.eliminations.clear();
+    info!("Round {} starts", server.round + 1);
+}
+
+fn end_round(mut server: ResMut<Server>) {
+    server.round += 1;
+}
+
+fn apply_intents(server: Res<Server>, mut query: Query<(&Player, &mut PlayerIntent)>) {
+    for (player, mut intent) in &mut query {
+        *intent = server.intents.get(player.0).copied().unwrap_or_default();
+    }
+}
+
+fn record_eliminations(
+    mut server: ResMut<Server>,
+    mut eliminations: EventReader<PlayerEliminated>,
+) {
+    server.eliminations.extend(eliminations.read().copied());
+}
+
+fn broadcast_snapshot(
+    mut server: ResMut<Server>,
+    round_outcome: Option<Res<RoundOutcome>>,
+    match_score: Res<MatchScore>,
+    water: Res<Water>,
+    query: Query<(&Player, &Transform, &Health)>,
+    pickups: Query<(&Rollback, &Pickup, &Transform)>,
+) {
+    server.tick += 1;
+    let mut tablets: Vec<TabletState> = query
+        .iter()
+        .map(|(player, transform, health)| TabletState {
+            player: player.0,
+            translation: transform.translation.truncate(),
+            rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+            scale: transform.scale.x,
+            health: health.0,
+        })
+        .collect();
+    tablets.sort_by_key(|tablet| tablet.player);
+    let mut pickups: Vec<PickupState> = pickups
+        .iter()
+        .map(|(rollback, pickup, transform)| PickupState {
+            id: rollback.id(),
+            kind: pickup.kind,
+            translation: transform.translation.truncate(),
+            rotation: transform.rotation.to_euler(EulerRot::XYZ).2,
+            scale: transform.scale.x,
+        })
+        .collect();
+    pickups.sort_by_key(|pickup| pickup.id);
+    let snapshot = ServerMessage::Snapshot(Snapshot {
+        round: server.round,
+        tick: server.tick,
+        tablets,
+        pickups,
+        water_level: water.level,
+        outcome: round_outcome.map(|outcome| *outcome),
+        wins: match_score.wins.clone(),
+        eliminations: server.eliminations.clone(),
+    });
+    for &client in server.clients.iter().chain(&server.spectators) {
+        server.send(&snapshot, client);
+    }
+}
+
+// a decided match starts over with the same players
+fn next_round(
+    mut server: ResMut<Server>,
+    config: Res<ServerConfig>,
+    teams: Res<Teams>,
+    mut match_score: ResMut<MatchScore>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    server.round_break = server.round_break.saturating_sub(1);
+    if server.round_break > 0 {
+        return;
+    }
+    if match_score.match_winner().is_some() {
+        *match_score = MatchScore::new(teams.side_count(config.player_number), config.best_of);
+    }
+    app_state.set(AppState::RestartRound);
+}
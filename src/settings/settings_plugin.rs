@@ -0,0 +1,206 @@
+use bevy::{
+    app::{Plugin, Update},
+    prelude::{DetectChanges, OnExit, Query, Res, Resource, With},
+    window::{MonitorSelection, PresentMode, PrimaryWindow, Window, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{persistence, MainMenuState, PauseState};
+
+const SETTINGS_KEY: &str = "settings";
+
+pub const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "Windowed",
+            DisplayMode::Borderless => "Borderless",
+            DisplayMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    pub fn next(&self) -> DisplayMode {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            DisplayMode::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeSlider {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeSlider {
+    pub const ALL: [VolumeSlider; 3] =
+        [VolumeSlider::Master, VolumeSlider::Music, VolumeSlider::Sfx];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VolumeSlider::Master => "Master",
+            VolumeSlider::Music => "Music",
+            VolumeSlider::Sfx => "SFX",
+        }
+    }
+}
+
+/// Audio and display preferences, edited from the settings screens and saved across sessions.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub display_mode: DisplayMode,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    /// Tablets far from the glass get a small view of their own instead of zooming the camera out.
+    pub picture_in_picture: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            master_volume: 1.,
+            music_volume: 1.,
+            sfx_volume: 1.,
+            display_mode: DisplayMode::Windowed,
+            resolution: (1920, 1080),
+            vsync: true,
+            picture_in_picture: false,
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn load() -> Self {
+        persistence::load(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        persistence::save(SETTINGS_KEY, self);
+    }
+
+    pub fn volume(&self, slider: VolumeSlider) -> f32 {
+        match slider {
+            VolumeSlider::Master => self.master_volume,
+            VolumeSlider::Music => self.music_volume,
+            VolumeSlider::Sfx => self.sfx_volume,
+        }
+    }
+
+    pub fn set_volume(&mut self, slider: VolumeSlider, volume: f32) {
+        let volume = volume.clamp(0., 1.);
+        match slider {
+            VolumeSlider::Master => self.master_volume = volume,
+            VolumeSlider::Music => self.music_volume = volume,
+            VolumeSlider::Sfx => self.sfx_volume = volume,
+        }
+    }
+
+    /// Factor applied to the volume of every music.
+    pub fn music_gain(&self) -> f64 {
+        (self.master_volume * self.music_volume) as f64
+    }
+
+    /// Factor applied to the volume of every sound effect.
+    pub fn sfx_gain(&self) -> f64 {
+        (self.master_volume * self.sfx_volume) as f64
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn next_resolution(&self) -> (u32, u32) {
+        let current = RESOLUTIONS.iter().position(|r| *r == self.resolution);
+        match current {
+            Some(index) => RESOLUTIONS[(index + 1) % RESOLUTIONS.len()],
+            None => RESOLUTIONS[0],
+        }
+    }
+
+    pub fn resolution_label(&self) -> String {
+        format!("{}x{}", self.resolution.0, self.resolution.1)
+    }
+
+    pub fn vsync_label(&self) -> &'static str {
+        if self.vsync {
+            "On"
+        } else {
+            "Off"
+        }
+    }
+
+    pub fn picture_in_picture_label(&self) -> &'static str {
+        if self.picture_in_picture {
+            "On"
+        } else {
+            "Off"
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(Update, apply_display_settings);
+        // sliders change the settings every frame while dragged, the file is written once on leaving
+        app.add_systems(OnExit(MainMenuState::Settings), save_settings);
+        app.add_systems(OnExit(PauseState::Settings), save_settings);
+    }
+}
+
+fn apply_display_settings(
+    settings: Res<GameSettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    for mut window in &mut window_query {
+        let mode = settings.display_mode.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        let (width, height) = settings.resolution;
+        if window.resolution.width() != width as f32 || window.resolution.height() != height as f32
+        {
+            window.resolution.set(width as f32, height as f32);
+        }
+        let present_mode = settings.present_mode();
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+fn save_settings(settings: Res<GameSettings>) {
+    settings.save();
+}
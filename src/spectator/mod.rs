@@ -0,0 +1 @@
+pub mod spectator_plugin;
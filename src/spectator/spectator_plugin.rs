@@ -0,0 +1,224 @@
+use bevy::{
+    app::{Plugin, Update},
+    color::Color,
+    input::{
+        mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseButton, MouseScrollUnit},
+        ButtonInput,
+    },
+    math::{Vec2, Vec3},
+    prelude::{
+        in_state, Camera2d, Commands, Component, IntoSystemConfigs, KeyCode, OnEnter,
+        OrthographicProjection, Query, Res, ResMut, Resource, Text, Transform, Visibility, With,
+        Without,
+    },
+    text::{TextColor, TextFont},
+    ui::{Node, PositionType, Val},
+    utils::default,
+};
+
+use crate::{
+    game_tuning::game_tuning_plugin::GameTuning, lobby::lobby_plugin::PlayerColors,
+    picture_in_picture::picture_in_picture_plugin::PipCamera,
+    player_input::player_input_plugin::PlayerDevices, AppState, FontAssets, Health, InGame,
+    PauseState, Player,
+};
+
+// camera scale while following a tablet
+const FOLLOW_SCALE: f32 = 1.5;
+// zoom per wheel notch, and per pixel on a touchpad
+const LINE_ZOOM: f32 = 0.1;
+const PIXEL_ZOOM: f32 = 0.002;
+
+/// What the camera looks at during a round.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraView {
+    /// Frames the glass and every tablet still fizzing.
+    #[default]
+    Match,
+    /// Stays on the tablet of a `Player` index, fizzing or not.
+    Follow(usize),
+    /// Dragged with the mouse and zoomed with the wheel.
+    Free,
+}
+
+#[derive(Component)]
+struct SpectatorOverlay;
+
+/// Camera controls for whoever is only watching: spectator clients, replays
+/// and players whose tablet is out. They work for everyone, the overlay shows once they matter.
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<CameraView>();
+        app.add_systems(OnEnter(AppState::InGame), spawn_overlay);
+        app.add_systems(
+            Update,
+            (spectator_controls, follow_camera, update_overlay)
+                .chain()
+                .run_if(in_state(PauseState::Running)),
+        );
+        app.add_systems(OnEnter(AppState::MainMenu), reset_view);
+    }
+}
+
+// Tab follows the next tablet and Shift+Tab the previous one, F1 frames the match again,
+// F2, a drag or the wheel free the camera.
+fn spectator_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    tuning: Res<GameTuning>,
+    mut view: ResMut<CameraView>,
+    player_query: Query<&Player>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<Camera2d>, Without<PipCamera>),
+    >,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        let mut players: Vec<usize> = player_query.iter().map(|player| player.0).collect();
+        players.sort();
+        let backwards = keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight);
+        let current = match *view {
+            CameraView::Follow(player) => players.iter().position(|p| *p == player),
+            _ => None,
+        };
+        let next = match (current, backwards) {
+            (Some(index), false) => (index + 1) % players.len(),
+            (Some(index), true) => (index + players.len() - 1) % players.len(),
+            (None, false) => 0,
+            (None, true) => players.len().saturating_sub(1),
+        };
+        if let Some(player) = players.get(next) {
+            *view = CameraView::Follow(*player);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        *view = CameraView::Match;
+    }
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        *view = CameraView::Free;
+    }
+
+    let dragging = mouse_input.pressed(MouseButton::Left) && mouse_motion.delta != Vec2::ZERO;
+    let zoom = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y * LINE_ZOOM,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y * PIXEL_ZOOM,
+    };
+    if dragging || zoom != 0. {
+        *view = CameraView::Free;
+    }
+    if *view != CameraView::Free {
+        return;
+    }
+
+    for (mut transform, mut projection) in &mut camera_query {
+        // screen y points down
+        let pan = Vec2::new(-mouse_motion.delta.x, mouse_motion.delta.y) * projection.scale;
+        if dragging {
+            transform.translation += pan.extend(0.);
+        }
+        projection.scale =
+            (projection.scale * (1. - zoom)).clamp(tuning.cam_zoom_min, tuning.cam_zoom_max);
+    }
+}
+
+fn follow_camera(
+    tuning: Res<GameTuning>,
+    view: Res<CameraView>,
+    player_query: Query<(&Player, &Transform), Without<Camera2d>>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<Camera2d>, Without<PipCamera>),
+    >,
+) {
+    let CameraView::Follow(followed) = *view else {
+        return;
+    };
+    let Some((_, target)) = player_query.iter().find(|(player, _)| player.0 == followed) else {
+        return;
+    };
+    let target_position = Vec3::new(target.translation.x, target.translation.y, 0.);
+    for (mut transform, mut projection) in &mut camera_query {
+        transform.translation = tuning.cam_elasticity * transform.translation
+            + (1.0 - tuning.cam_elasticity) * target_position;
+        projection.scale =
+            tuning.cam_elasticity * projection.scale + (1.0 - tuning.cam_elasticity) * FOLLOW_SCALE;
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands.spawn((
+        InGame,
+        SpectatorOverlay,
+        Text::default(),
+        TextFont {
+            font: font_assets.bold.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Visibility::Hidden,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.),
+            left: Val::Px(20.),
+            ..default()
+        },
+    ));
+}
+
+// shown once a view is picked, or right away when no tablet played here is fizzing
+fn update_overlay(
+    view: Res<CameraView>,
+    devices: Res<PlayerDevices>,
+    player_colors: Res<PlayerColors>,
+    player_query: Query<(&Player, &Health)>,
+    mut query: Query<(&mut Text, &mut TextColor, &mut Visibility), With<SpectatorOverlay>>,
+) {
+    let mut players: Vec<(usize, bool)> = player_query
+        .iter()
+        .map(|(player, health)| (player.0, health.0 > 0.))
+        .collect();
+    players.sort();
+    let watching = players
+        .iter()
+        .all(|(player, fizzing)| !fizzing || devices.0.get(*player).copied().flatten().is_none());
+
+    let (title, color) = match *view {
+        CameraView::Match => ("Watching the whole glass".to_string(), Color::WHITE),
+        CameraView::Follow(player) => (
+            format!("Watching Player {}", player + 1),
+            player_colors.get(player),
+        ),
+        CameraView::Free => ("Free camera".to_string(), Color::WHITE),
+    };
+    let mut lines = vec![title];
+    for (player, fizzing) in &players {
+        let marker = if *view == CameraView::Follow(*player) {
+            ">"
+        } else {
+            " "
+        };
+        let state = if *fizzing { "" } else { "  (out)" };
+        lines.push(format!("{marker} Player {}{state}", player + 1));
+    }
+    lines.push("[Tab] next tablet  [F1] whole glass  [F2] / mouse free camera".to_string());
+
+    for (mut text, mut text_color, mut visibility) in &mut query {
+        text.0 = lines.join("\n");
+        text_color.0 = color;
+        *visibility = if watching || *view != CameraView::Match {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn reset_view(mut view: ResMut<CameraView>) {
+    *view = CameraView::Match;
+}
@@ -0,0 +1 @@
+pub mod tablet_plugin;
@@ -0,0 +1,151 @@
+use avian2d::prelude::{Collider, ComputeMassProperties2d};
+use bevy::{
+    app::Plugin,
+    asset::{Asset, Assets, Handle},
+    math::Vec2,
+    prelude::{Component, Resource},
+    reflect::TypePath,
+};
+use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_common_assets::ron::RonAssetPlugin;
+use serde::{Deserialize, Serialize};
+
+use crate::{game_tuning::game_tuning_plugin::GameTuning, rollback::rollback_plugin::RollbackApp};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum TabletShape {
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    Circle {
+        radius: f32,
+    },
+    /// Lying capsule, `length` is the distance between the centres of its round ends.
+    Capsule {
+        radius: f32,
+        length: f32,
+    },
+}
+
+impl TabletShape {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            TabletShape::Rectangle { width, height } => Collider::rectangle(width, height),
+            TabletShape::Circle { radius } => Collider::circle(radius),
+            TabletShape::Capsule { radius, length } => Collider::capsule_endpoints(
+                radius,
+                Vec2::new(-length / 2., 0.),
+                Vec2::new(length / 2., 0.),
+            ),
+        }
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        match *self {
+            TabletShape::Rectangle { width, height } => Vec2::new(width, height) / 2.,
+            TabletShape::Circle { radius } => Vec2::splat(radius),
+            TabletShape::Capsule { radius, length } => Vec2::new(length / 2. + radius, radius),
+        }
+    }
+}
+
+/// Shape and stats of a kind of tablet, loaded from `assets/tablets/*.tablet.ron`.
+/// The scales multiply the matching values of the `GameTuning`.
+#[derive(Asset, TypePath, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TabletType {
+    pub name: String,
+    pub shape: TabletShape,
+    pub density_scale: f32,
+    pub health_scale: f32,
+    pub thrust_scale: f32,
+    /// Scales the impact damage the tablet takes.
+    pub damage_scale: f32,
+}
+
+impl Default for TabletType {
+    fn default() -> Self {
+        TabletType {
+            name: "Classic".to_string(),
+            shape: TabletShape::Rectangle {
+                width: 128.,
+                height: 0.2 * 128.,
+            },
+            density_scale: 1.,
+            health_scale: 1.,
+            thrust_scale: 1.,
+            damage_scale: 1.,
+        }
+    }
+}
+
+/// Stats of the `TabletType` a `Player` spawned with.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Tablet {
+    pub max_health: f32,
+    pub density: f32,
+    pub thrust_scale: f32,
+    pub damage_scale: f32,
+    /// Half of the width and height at full health, the thrusters sit on its edges.
+    pub half_size: Vec2,
+    pub base_volume: f32,
+}
+
+impl Tablet {
+    pub fn new(tablet_type: &TabletType, collider: &Collider, tuning: &GameTuning) -> Self {
+        Tablet {
+            max_health: tuning.initial_health * tablet_type.health_scale,
+            density: tuning.cachet_density * tablet_type.density_scale,
+            thrust_scale: tablet_type.thrust_scale,
+            damage_scale: tablet_type.damage_scale,
+            half_size: tablet_type.shape.half_size(),
+            base_volume: collider.mass(1.),
+        }
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct TabletAssets {
+    #[asset(
+        paths(
+            "tablets/classic.tablet.ron",
+            "tablets/aspirin.tablet.ron",
+            "tablets/vitamin_c.tablet.ron",
+            "tablets/capsule.tablet.ron"
+        ),
+        collection(typed)
+    )]
+    pub types: Vec<Handle<TabletType>>,
+}
+
+impl TabletAssets {
+    /// The type at `index`, the classic tablet if the files are not loaded.
+    pub fn get(&self, index: usize, tablet_types: &Assets<TabletType>) -> TabletType {
+        self.types
+            .get(index)
+            .and_then(|handle| tablet_types.get(handle))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Tablet type of each `Player` index for the match, missing entries play the classic tablet.
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct PlayerTablets(pub Vec<TabletType>);
+
+impl PlayerTablets {
+    pub fn get(&self, player: usize) -> TabletType {
+        self.0.get(player).cloned().unwrap_or_default()
+    }
+}
+
+pub struct TabletPlugin;
+
+impl Plugin for TabletPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_plugins(RonAssetPlugin::<TabletType>::new(&["tablet.ron"]));
+        app.init_resource::<PlayerTablets>();
+        app.rollback_component::<Tablet>();
+    }
+}
@@ -0,0 +1 @@
+pub mod thin_client_plugin;
@@ -0,0 +1,455 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+};
+
+use avian2d::prelude::{Collider, Physics, PhysicsTime};
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    log::warn,
+    math::{Quat, Vec3},
+    prelude::{
+        in_state, resource_exists, resource_removed, Commands, Component, DespawnRecursiveExt,
+        Entity, EventWriter, IntoSystemConfigs, NextState, OnExit, Query, Res, ResMut, Resource,
+        Transform, Without,
+    },
+    time::{Fixed, Time},
+};
+
+use crate::{
+    bot::bot_plugin::BotPlayers,
+    game_match::game_match_plugin::MatchScore,
+    hazard::hazard_plugin::Hazards,
+    lobby::lobby_plugin::PlayerColors,
+    pickup::pickup_plugin::Pickup,
+    player_input::player_input_plugin::{InputDevice, PlayerDevices},
+    server::server_plugin::{
+        ClientMessage, MatchSetup, PickupState, ServerMessage, Snapshot, TabletState,
+        MAX_PACKET_SIZE,
+    },
+    water::water_plugin::Water,
+    AppState, Health, InGame, Player, PlayerEliminated, PlayerIntent, PlayerNumber, RoundOutcome,
+};
+
+// ticks the mirrored round is shown behind the latest snapshot, so that one is mostly known on each side
+const INTERPOLATION_DELAY: f32 = 4.;
+const MAX_SNAPSHOTS: usize = 64;
+
+/// Reads `<flag> <address of a tablet-server>` from the command line, `--connect` to play and `--spectate` to watch.
+pub fn server_address(args: &[String], flag: &str) -> Option<SocketAddr> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1)?.parse().ok()
+}
+
+// item of the server shown here, by its id in the snapshots
+#[derive(Component)]
+struct MirroredPickup(u32);
+
+/// Connection to a `tablet-server`: the match is played there and only mirrored here.
+#[derive(Resource)]
+pub struct ThinClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    spectator: bool,
+    player: Option<usize>,
+    setup: Option<MatchSetup>,
+    // round being shown, the first one snapshots arrive for until then
+    round: Option<u32>,
+    // last round whose outcome was shown, its late snapshots are ignored
+    decided: Option<u32>,
+    // snapshots of that round by tick
+    snapshots: VecDeque<Snapshot>,
+    render_tick: f32,
+    // eliminations of the round already sent as events
+    announced: usize,
+}
+
+impl ThinClient {
+    pub fn connect(server: SocketAddr) -> std::io::Result<Self> {
+        Self::open(server, false)
+    }
+
+    /// Connects without a tablet, to watch the match the server plays.
+    pub fn spectate(server: SocketAddr) -> std::io::Result<Self> {
+        Self::open(server, true)
+    }
+
+    fn open(server: SocketAddr, spectator: bool) -> std::io::Result<Self> {
+        let local: SocketAddr = if server.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(ThinClient {
+            socket,
+            server,
+            spectator,
+            player: None,
+            setup: None,
+            round: None,
+            decided: None,
+            snapshots: VecDeque::new(),
+            render_tick: 0.,
+            announced: 0,
+        })
+    }
+
+    /// `Player` index the server gave this client, `None` until it answers and for a spectator.
+    pub fn player(&self) -> Option<usize> {
+        self.player
+    }
+
+    pub fn setup(&self) -> Option<&MatchSetup> {
+        self.setup.as_ref()
+    }
+
+    /// Last tick received of the round being shown.
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+
+    /// Asks to join until the server answers, then tells it what the local player wants their tablet to do.
+    pub fn send(&self, intent: PlayerIntent) {
+        let message = match (self.setup.is_some(), self.spectator) {
+            (false, false) => ClientMessage::Join,
+            (false, true) => ClientMessage::Spectate,
+            (true, false) => ClientMessage::Intent(intent),
+            // nothing to say once welcomed
+            (true, true) => return,
+        };
+        let Ok(text) = ron::to_string(&message) else {
+            return;
+        };
+        if let Err(err) = self.socket.send_to(text.as_bytes(), self.server) {
+            if err.kind() != ErrorKind::WouldBlock {
+                warn!("Could not send to the server: {err}");
+            }
+        }
+    }
+
+    pub fn receive(&mut self) {
+        let mut buffer = vec![0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, address)) => {
+                    if address != self.server {
+                        continue;
+                    }
+                    let message = std::str::from_utf8(&buffer[..size])
+                        .ok()
+                        .and_then(|text| ron::from_str::<ServerMessage>(text).ok());
+                    if let Some(message) = message {
+                        self.accept(message);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // a server that is not listening yet makes some platforms report an error
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => {
+                    warn!("Could not receive from the server: {err}");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn accept(&mut self, message: ServerMessage) {
+        let snapshot = match message {
+            ServerMessage::Welcome { player, setup } => {
+                self.player = player;
+                self.setup = Some(*setup);
+                return;
+            }
+            ServerMessage::Snapshot(snapshot) => snapshot,
+        };
+        if self
+            .decided
+            .is_some_and(|decided| snapshot.round <= decided)
+        {
+            return;
+        }
+        let round = *self.round.get_or_insert(snapshot.round);
+        if snapshot.round < round {
+            return;
+        }
+        // the server moved on without this client
+        if snapshot.round > round {
+            self.round = Some(snapshot.round);
+            self.snapshots.clear();
+            self.announced = 0;
+        }
+        let index = self
+            .snapshots
+            .partition_point(|known| known.tick < snapshot.tick);
+        if self
+            .snapshots
+            .get(index)
+            .is_some_and(|known| known.tick == snapshot.tick)
+        {
+            return;
+        }
+        self.snapshots.insert(index, snapshot);
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Tablets at `tick` of the round being shown, blended between the snapshots on each side of it.
+    pub fn sample(&self, tick: f32) -> Vec<TabletState> {
+        let Some((from, to, t)) = self.around(tick) else {
+            return Vec::new();
+        };
+        from.tablets
+            .iter()
+            .filter_map(|from| {
+                let to = to.tablets.iter().find(|to| to.player == from.player)?;
+                Some(from.lerp(to, t))
+            })
+            .collect()
+    }
+
+    /// Items at `tick` of the round being shown, blended like the tablets.
+    pub fn sample_pickups(&self, tick: f32) -> Vec<PickupState> {
+        let Some((from, to, t)) = self.around(tick) else {
+            return Vec::new();
+        };
+        from.pickups
+            .iter()
+            .filter_map(|from| {
+                let to = to.pickups.iter().find(|to| to.id == from.id)?;
+                Some(from.lerp(to, t))
+            })
+            .collect()
+    }
+
+    // snapshots on each side of `tick` and how far it is between them, the same one twice past either end
+    fn around(&self, tick: f32) -> Option<(&Snapshot, &Snapshot, f32)> {
+        let latest = self.latest()?;
+        let Some(after) = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.tick as f32 >= tick)
+        else {
+            return Some((latest, latest, 0.));
+        };
+        let to = &self.snapshots[after];
+        let Some(from) = after.checked_sub(1).map(|before| &self.snapshots[before]) else {
+            return Some((to, to, 0.));
+        };
+        let t = (tick - from.tick as f32) / (to.tick - from.tick) as f32;
+        Some((from, to, t))
+    }
+
+    // moves the shown tick on by `ticks`, jumping back in line after a stall or a burst of snapshots
+    fn advance(&mut self, ticks: f32) -> Option<f32> {
+        let latest = self.latest()?.tick as f32;
+        let target = latest - INTERPOLATION_DELAY;
+        let tick = self.render_tick + ticks;
+        self.render_tick = if (tick - target).abs() > INTERPOLATION_DELAY {
+            target
+        } else {
+            tick.min(latest)
+        };
+        Some(self.render_tick)
+    }
+
+    // the next round is whichever the server sends next, it may already have started
+    fn leave_round(&mut self) {
+        // snapshots of a round after the decided one already arrived
+        if self.round > self.decided {
+            return;
+        }
+        self.round = None;
+        self.snapshots.clear();
+        self.render_tick = 0.;
+        self.announced = 0;
+    }
+}
+
+/// Plays on a `tablet-server` given by a `ThinClient`: the local simulation is turned off
+/// and the tablets follow the snapshots of the server instead.
+pub struct ThinClientPlugin;
+
+impl Plugin for ThinClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, stop_physics.run_if(resource_exists::<ThinClient>));
+        app.add_systems(
+            Update,
+            resume_physics.run_if(resource_removed::<ThinClient>),
+        );
+        app.add_systems(
+            Update,
+            (
+                receive_snapshots,
+                join_server.run_if(in_state(AppState::MainMenu)),
+                mirror_round.run_if(in_state(AppState::InGame)),
+                send_intent,
+            )
+                .chain()
+                .run_if(resource_exists::<ThinClient>),
+        );
+        app.add_systems(
+            OnExit(AppState::InGame),
+            leave_round.run_if(resource_exists::<ThinClient>),
+        );
+    }
+}
+
+// the tablets spawned each round stay where the snapshots put them
+fn stop_physics(mut time: ResMut<Time<Physics>>) {
+    time.set_relative_speed(0.);
+}
+
+// a player who quit the server plays locally again
+fn resume_physics(mut time: ResMut<Time<Physics>>) {
+    time.set_relative_speed(1.);
+}
+
+fn receive_snapshots(mut client: ResMut<ThinClient>) {
+    client.receive();
+}
+
+// the server picks the match, the local player only brings their keyboard and a spectator nothing
+fn join_server(
+    mut commands: Commands,
+    client: Res<ThinClient>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(setup) = client.setup.as_ref() else {
+        return;
+    };
+    let mut devices = vec![None; setup.player_number];
+    if let Some(player) = client.player {
+        devices[player] = Some(InputDevice::Keyboard(0));
+    }
+
+    commands.insert_resource(PlayerNumber(setup.player_number));
+    commands.insert_resource(BotPlayers::default());
+    commands.insert_resource(PlayerDevices(devices));
+    commands.insert_resource(PlayerColors::default());
+    commands.insert_resource(setup.tablets.clone());
+    commands.insert_resource(setup.teams.clone());
+    commands.insert_resource(MatchScore::new(
+        setup.teams.side_count(setup.player_number),
+        setup.best_of,
+    ));
+    commands.insert_resource(setup.tuning.clone());
+    commands.insert_resource(setup.arena.clone());
+    commands.insert_resource(Hazards::default());
+    app_state.set(AppState::InGame);
+}
+
+fn mirror_round(
+    mut commands: Commands,
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+    mut client: ResMut<ThinClient>,
+    round_outcome: Option<Res<RoundOutcome>>,
+    mut match_score: ResMut<MatchScore>,
+    mut eliminated: EventWriter<PlayerEliminated>,
+    mut water: ResMut<Water>,
+    mut query: Query<(Entity, &Player, &mut Transform, &mut Health)>,
+    mut mirrored_pickups: Query<(Entity, &MirroredPickup, &mut Transform), Without<Player>>,
+) {
+    // the server started the next round before the outcome of this one was over
+    if round_outcome.is_some() && client.round != client.decided {
+        return;
+    }
+    let ticks = time.delta_secs() / fixed_time.timestep().as_secs_f32();
+    let Some(render_tick) = client.advance(ticks) else {
+        return;
+    };
+    let tablets = client.sample(render_tick);
+    for (entity, player, mut transform, mut health) in &mut query {
+        let Some(tablet) = tablets.iter().find(|tablet| tablet.player == player.0) else {
+            // dissolved on the server
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        transform.translation = tablet.translation.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(tablet.rotation);
+        transform.scale = Vec3::new(tablet.scale, tablet.scale, 1.);
+        health.0 = tablet.health;
+    }
+
+    // items only move where the server puts them, nothing lands, melts or gets grabbed here
+    let pickups = client.sample_pickups(render_tick);
+    for (entity, mirrored, mut transform) in &mut mirrored_pickups {
+        let Some(pickup) = pickups.iter().find(|pickup| pickup.id == mirrored.0) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        transform.translation = pickup.translation.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(pickup.rotation);
+        transform.scale = Vec3::new(pickup.scale, pickup.scale, 1.);
+    }
+    for pickup in &pickups {
+        if mirrored_pickups
+            .iter()
+            .any(|(_, mirrored, _)| mirrored.0 == pickup.id)
+        {
+            continue;
+        }
+        let size = pickup.kind.size();
+        commands.spawn((
+            InGame,
+            MirroredPickup(pickup.id),
+            Pickup {
+                kind: pickup.kind,
+                ticks_left: 0,
+                landed: false,
+            },
+            Collider::rectangle(size.x, size.y),
+            Transform::from_translation(pickup.translation.extend(0.))
+                .with_rotation(Quat::from_rotation_z(pickup.rotation))
+                .with_scale(Vec3::new(pickup.scale, pickup.scale, 1.)),
+        ));
+    }
+    if let Some(latest) = client.latest() {
+        if water.level != latest.water_level {
+            water.level = latest.water_level;
+        }
+    }
+
+    let announced = client.announced;
+    let new_eliminations: Vec<PlayerEliminated> = client
+        .latest()
+        .map(|latest| {
+            latest
+                .eliminations
+                .iter()
+                .skip(announced)
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default();
+    client.announced += new_eliminations.len();
+    eliminated.send_batch(new_eliminations);
+
+    if round_outcome.is_some() {
+        return;
+    }
+    if let Some(latest) = client.latest() {
+        if let Some(outcome) = latest.outcome {
+            match_score.wins = latest.wins.clone();
+            commands.insert_resource(outcome);
+            client.decided = client.round;
+        }
+    }
+}
+
+fn send_intent(client: Res<ThinClient>, query: Query<(&Player, &PlayerIntent)>) {
+    let intent = query
+        .iter()
+        .find(|(player, _)| Some(player.0) == client.player)
+        .map(|(_, intent)| *intent)
+        .unwrap_or_default();
+    client.send(intent);
+}
+
+fn leave_round(mut client: ResMut<ThinClient>) {
+    client.leave_round();
+}
@@ -0,0 +1 @@
+pub mod water_plugin;
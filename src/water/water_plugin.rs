@@ -0,0 +1,295 @@
+use avian2d::prelude::{Collider, LinearVelocity};
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    math::{Vec2, Vec3, Vec3Swizzles},
+    prelude::{
+        in_state, Commands, Component, IntoSystemConfigs, OnEnter, Query, Res, ResMut, Resource,
+        Transform,
+    },
+};
+
+use crate::{
+    arena::arena_plugin::Arena, game_tuning::game_tuning_plugin::GameTuning,
+    rollback::rollback_plugin::RollbackApp, use_turbo, AppState, PauseState, SimulationSet, Volume,
+};
+
+/// Bodies count as out of the water a bit below the drawn surface.
+pub const SURFACE_MARGIN: f32 = 10.;
+// distance between two spring columns of the surface
+const COLUMN_SPACING: f32 = 16.;
+// neighbour propagation passes per tick, more passes make faster waves
+const SPREAD_PASSES: usize = 8;
+
+#[derive(Clone, Copy, Default)]
+struct WaterColumn {
+    offset: f32,
+    velocity: f32,
+}
+
+/// Water of the glass: a rest level and a surface made of spring columns along which waves travel.
+#[derive(Resource, Clone)]
+pub struct Water {
+    pub level: f32,
+    pub left: f32,
+    pub right: f32,
+    /// Lowest floor of the water, it follows the walls above it.
+    pub bottom: f32,
+    columns: Vec<WaterColumn>,
+    // floor under each column
+    floor: Vec<f32>,
+}
+
+impl Default for Water {
+    fn default() -> Self {
+        Water::from_arena(&Arena::default())
+    }
+}
+
+impl Water {
+    pub fn new(level: f32, left: f32, right: f32, bottom: f32) -> Self {
+        let column_number = ((right - left) / COLUMN_SPACING).ceil() as usize + 1;
+        Water {
+            level,
+            left,
+            right,
+            bottom,
+            columns: vec![WaterColumn::default(); column_number.max(2)],
+            floor: vec![bottom; column_number.max(2)],
+        }
+    }
+
+    /// Water of `arena`, resting on its walls.
+    pub fn from_arena(arena: &Arena) -> Self {
+        let mut water = Water::new(
+            arena.water.level - SURFACE_MARGIN,
+            arena.water.left,
+            arena.water.right,
+            arena.water.bottom,
+        );
+        let spacing = water.spacing();
+        water.floor = (0..water.columns.len())
+            .map(|i| arena.floor_at(water.left + i as f32 * spacing))
+            .collect();
+        water
+    }
+
+    fn spacing(&self) -> f32 {
+        (self.right - self.left) / (self.columns.len() - 1) as f32
+    }
+
+    /// Points of the surface from left to right.
+    pub fn surface_points(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let spacing = self.spacing();
+        self.columns.iter().enumerate().map(move |(i, column)| {
+            Vec2::new(self.left + i as f32 * spacing, self.level + column.offset)
+        })
+    }
+
+    // column on the left of `x` and how far `x` is towards the next one
+    fn locate(&self, x: f32) -> (usize, f32) {
+        let position =
+            ((x - self.left) / self.spacing()).clamp(0., (self.columns.len() - 1) as f32);
+        let index = (position as usize).min(self.columns.len() - 2);
+        (index, position - index as f32)
+    }
+
+    pub fn surface_y(&self, x: f32) -> f32 {
+        let (index, t) = self.locate(x);
+        let offset = self.columns[index].offset * (1. - t) + self.columns[index + 1].offset * t;
+        self.level + offset
+    }
+
+    /// Floor of the water at `x`, the surface is under it where a wall closes the water off.
+    pub fn bottom_y(&self, x: f32) -> f32 {
+        let (index, t) = self.locate(x);
+        self.floor[index] * (1. - t) + self.floor[index + 1] * t
+    }
+
+    /// Height change of the surface per unit along x.
+    pub fn slope(&self, x: f32) -> f32 {
+        let (index, _) = self.locate(x);
+        (self.columns[index + 1].offset - self.columns[index].offset) / self.spacing()
+    }
+
+    pub fn contains(&self, translation: &Vec3) -> bool {
+        translation.y <= self.surface_y(translation.x)
+            && translation.y >= self.bottom_y(translation.x)
+            && translation.x >= self.left
+            && translation.x <= self.right
+    }
+
+    /// Share of a body under the surface, and the centre of buoyancy relative to its translation.
+    /// Bodies without a collider are treated as points.
+    pub fn submerged(&self, collider: Option<&Collider>, transform: &Transform) -> (f32, Vec2) {
+        let center = transform.translation.xy();
+        if center.x < self.left || center.x > self.right || center.y < self.bottom_y(center.x) {
+            return (0., Vec2::ZERO);
+        }
+        // the surface is taken as straight across a body
+        let surface_y = self.surface_y(center.x);
+        let slope = self.slope(center.x);
+        let depth = |point: Vec2| surface_y + slope * (point.x - center.x) - point.y;
+
+        let shape = collider.map(|collider| collider.shape_scaled());
+        if let Some(ball) = shape.and_then(|shape| shape.as_ball()) {
+            let radius = ball.radius;
+            // height of the surface over the centre, measured across the slope
+            let height = (depth(center) / (1. + slope * slope).sqrt()).clamp(-radius, radius);
+            let dry = radius * radius * (height / radius).acos()
+                - height * (radius * radius - height * height).sqrt();
+            let total = std::f32::consts::PI * radius * radius;
+            (1. - dry / total, Vec2::ZERO)
+        } else if let Some(shape) = shape {
+            // anything but a ball is clipped as its bounding box, exact for the rectangles
+            let half_extents = shape.compute_local_aabb().half_extents();
+            let half = Vec2::new(half_extents.x, half_extents.y);
+            let corners = [
+                Vec2::new(-half.x, -half.y),
+                Vec2::new(half.x, -half.y),
+                Vec2::new(half.x, half.y),
+                Vec2::new(-half.x, half.y),
+            ]
+            .map(|corner| center + (transform.rotation * corner.extend(0.)).xy());
+
+            // Sutherland-Hodgman clip of the rectangle by the half plane under the surface
+            let mut wet = Vec::with_capacity(5);
+            for (i, &from) in corners.iter().enumerate() {
+                let to = corners[(i + 1) % corners.len()];
+                let (from_depth, to_depth) = (depth(from), depth(to));
+                if from_depth >= 0. {
+                    wet.push(from);
+                }
+                if (from_depth >= 0.) != (to_depth >= 0.) {
+                    wet.push(from.lerp(to, from_depth / (from_depth - to_depth)));
+                }
+            }
+            let (area, centroid) = polygon_area_centroid(&wet);
+            let total = 4. * half.x * half.y;
+            if area <= 0. || total <= 0. {
+                return (0., Vec2::ZERO);
+            }
+            ((area / total).min(1.), centroid - center)
+        } else if depth(center) >= 0. {
+            (1., Vec2::ZERO)
+        } else {
+            (0., Vec2::ZERO)
+        }
+    }
+
+    /// Pushes the columns within `radius` of `x`, a negative `velocity` digs the surface.
+    pub fn splash(&mut self, x: f32, radius: f32, velocity: f32) {
+        let spacing = self.spacing();
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            let column_x = self.left + i as f32 * spacing;
+            if (column_x - x).abs() <= radius.max(spacing) {
+                column.velocity += velocity;
+            }
+        }
+    }
+
+    /// Splash of a body of `volume` crossing the surface, heavier bodies dig wider and deeper.
+    pub fn splash_body(&mut self, x: f32, volume: f32, velocity: f32) {
+        let size = volume.sqrt();
+        self.splash(x, size * 0.5, velocity * (size / 64.).min(1.));
+    }
+
+    fn step(&mut self, stiffness: f32, damping: f32, spread: f32) {
+        for column in self.columns.iter_mut() {
+            column.velocity += -stiffness * column.offset - damping * column.velocity;
+            column.offset += column.velocity;
+        }
+
+        let count = self.columns.len();
+        let mut left_deltas = vec![0.; count];
+        let mut right_deltas = vec![0.; count];
+        for _ in 0..SPREAD_PASSES {
+            for i in 0..count {
+                if i > 0 {
+                    left_deltas[i] = spread * (self.columns[i].offset - self.columns[i - 1].offset);
+                    self.columns[i - 1].velocity += left_deltas[i];
+                }
+                if i < count - 1 {
+                    right_deltas[i] =
+                        spread * (self.columns[i].offset - self.columns[i + 1].offset);
+                    self.columns[i + 1].velocity += right_deltas[i];
+                }
+            }
+            for i in 0..count {
+                if i > 0 {
+                    self.columns[i - 1].offset += left_deltas[i];
+                }
+                if i < count - 1 {
+                    self.columns[i + 1].offset += right_deltas[i];
+                }
+            }
+        }
+    }
+}
+
+fn polygon_area_centroid(points: &[Vec2]) -> (f32, Vec2) {
+    let mut area = 0.;
+    let mut centroid = Vec2::ZERO;
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let cross = a.perp_dot(b);
+        area += cross;
+        centroid += (a + b) * cross;
+    }
+    if area.abs() < f32::EPSILON {
+        return (0., Vec2::ZERO);
+    }
+    (area.abs() * 0.5, centroid / (3. * area))
+}
+
+/// Side of the surface a body was on last tick, to splash when it crosses.
+#[derive(Component, Clone, Default)]
+pub struct WaterContact(bool);
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<Water>();
+        app.rollback_resource::<Water>()
+            .rollback_component::<WaterContact>();
+        app.add_systems(OnEnter(AppState::InGame), reset_water);
+        app.add_systems(
+            FixedUpdate,
+            (splash_on_crossing, step_water)
+                .chain()
+                .before(use_turbo)
+                .in_set(SimulationSet)
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+fn reset_water(mut commands: Commands, arena: Res<Arena>) {
+    commands.insert_resource(Water::from_arena(&arena));
+}
+
+fn splash_on_crossing(
+    tuning: Res<GameTuning>,
+    mut water: ResMut<Water>,
+    mut query: Query<(&Transform, &LinearVelocity, &Volume, &mut WaterContact)>,
+) {
+    for (transform, velocity, volume, mut contact) in &mut query {
+        let in_water = water.contains(&transform.translation);
+        if in_water != contact.0 {
+            contact.0 = in_water;
+            water.splash_body(
+                transform.translation.x,
+                volume.0,
+                velocity.0.y * tuning.splash_scale,
+            );
+        }
+    }
+}
+
+pub(crate) fn step_water(tuning: Res<GameTuning>, mut water: ResMut<Water>) {
+    water.step(
+        tuning.wave_stiffness,
+        tuning.wave_damping,
+        tuning.wave_spread,
+    );
+}
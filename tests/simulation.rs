@@ -0,0 +1,483 @@
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_template::{
+    confirmed_world, headless_app, server_app, EliminationCause, GameRng, GameTuning, Health,
+    LastAttacker, MatchScore, NetConfig, NetSession, NetcodePlugin, PauseState, Player,
+    PlayerEliminated, PlayerIntent, PlayerTablets, RoundOutcome, RoundSeed, ServerConfig,
+    TabletType, Teams, ThinClient,
+};
+
+// one second of play at the default fixed timestep
+const SECOND: usize = 64;
+
+fn run_ticks(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+fn tablet(app: &mut App, index: usize) -> Entity {
+    let world = app.world_mut();
+    let mut query = world.query::<(Entity, &Player)>();
+    query
+        .iter(world)
+        .find(|(_, player)| player.0 == index)
+        .map(|(entity, _)| entity)
+        .expect("tablet should be spawned")
+}
+
+fn health(app: &App, entity: Entity) -> f32 {
+    app.world().get::<Health>(entity).unwrap().0
+}
+
+fn height(app: &App, entity: Entity) -> f32 {
+    app.world().get::<Transform>(entity).unwrap().translation.y
+}
+
+#[test]
+fn idle_tablet_dissolves_in_water() {
+    let mut app = headless_app(1);
+    run_ticks(&mut app, 2);
+    let entity = tablet(&mut app, 0);
+    let initial_health = health(&app, entity);
+
+    run_ticks(&mut app, SECOND);
+
+    assert!(health(&app, entity) < initial_health);
+}
+
+#[test]
+fn damaged_tablet_shrinks() {
+    let mut app = headless_app(1);
+    run_ticks(&mut app, 2);
+    let entity = tablet(&mut app, 0);
+    let initial_scale = app.world().get::<Transform>(entity).unwrap().scale.x;
+
+    let initial_health = health(&app, entity);
+    app.world_mut().get_mut::<Health>(entity).unwrap().0 = initial_health / 2.;
+    run_ticks(&mut app, 2);
+
+    assert!(app.world().get::<Transform>(entity).unwrap().scale.x < initial_scale);
+}
+
+#[test]
+fn tablet_type_scales_health() {
+    let mut app = headless_app(2);
+    let tanky = TabletType {
+        health_scale: 2.,
+        ..default()
+    };
+    app.insert_resource(PlayerTablets(vec![TabletType::default(), tanky]));
+    app.update();
+
+    let classic = tablet(&mut app, 0);
+    let tanky = tablet(&mut app, 1);
+    assert_eq!(health(&app, tanky), 2. * health(&app, classic));
+}
+
+#[test]
+fn up_thrust_rises_above_idle_tablet() {
+    let mut idle = headless_app(1);
+    let mut thrusting = headless_app(1);
+    run_ticks(&mut idle, 2);
+    run_ticks(&mut thrusting, 2);
+    let idle_entity = tablet(&mut idle, 0);
+    let thrusting_entity = tablet(&mut thrusting, 0);
+
+    for _ in 0..SECOND {
+        thrusting
+            .world_mut()
+            .get_mut::<PlayerIntent>(thrusting_entity)
+            .unwrap()
+            .up = 1.;
+        idle.update();
+        thrusting.update();
+    }
+
+    assert!(height(&thrusting, thrusting_entity) > height(&idle, idle_entity));
+}
+
+#[test]
+fn paused_round_is_frozen() {
+    let mut app = headless_app(1);
+    run_ticks(&mut app, SECOND);
+    let entity = tablet(&mut app, 0);
+
+    app.world_mut()
+        .resource_mut::<NextState<PauseState>>()
+        .set(PauseState::Paused);
+    app.update();
+    let paused_height = height(&app, entity);
+    let paused_health = health(&app, entity);
+    run_ticks(&mut app, SECOND);
+
+    assert_eq!(height(&app, entity), paused_height);
+    assert_eq!(health(&app, entity), paused_health);
+}
+
+#[test]
+fn tablet_below_the_glass_is_eliminated() {
+    let mut app = headless_app(1);
+    run_ticks(&mut app, 2);
+    let entity = tablet(&mut app, 0);
+
+    app.world_mut()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .y = -10_000.;
+    run_ticks(&mut app, 2);
+
+    assert_eq!(health(&app, entity), 0.);
+    assert_eq!(
+        app.world().get_resource::<RoundOutcome>(),
+        Some(&RoundOutcome { winner: None })
+    );
+}
+
+#[test]
+fn eliminations_tell_their_cause_and_attacker() {
+    let mut app = headless_app(3);
+    run_ticks(&mut app, 2);
+    let knocked_out = tablet(&mut app, 0);
+    let fizzing = tablet(&mut app, 1);
+
+    app.world_mut()
+        .get_mut::<LastAttacker>(knocked_out)
+        .unwrap()
+        .0 = Some(2);
+    app.world_mut()
+        .get_mut::<Transform>(knocked_out)
+        .unwrap()
+        .translation
+        .y = -10_000.;
+    // the default turbos heal a little, the water still takes the last of its health
+    app.world_mut().get_mut::<PlayerIntent>(fizzing).unwrap().up = 1.;
+    app.world_mut().get_mut::<Health>(fizzing).unwrap().0 = 0.01;
+
+    let mut eliminations = Vec::new();
+    for _ in 0..4 {
+        app.update();
+        eliminations.extend(
+            app.world_mut()
+                .resource_mut::<Events<PlayerEliminated>>()
+                .drain(),
+        );
+    }
+
+    assert_eq!(
+        eliminations,
+        [
+            PlayerEliminated {
+                player: 1,
+                cause: EliminationCause::Dissolved,
+                attacker: None,
+            },
+            PlayerEliminated {
+                player: 0,
+                cause: EliminationCause::KnockedOut,
+                attacker: Some(2),
+            },
+        ]
+    );
+}
+
+#[test]
+fn tablet_killed_by_its_own_turbo_fizzed_out() {
+    let mut app = headless_app(2);
+    app.insert_resource(GameTuning {
+        turbo_tick_damage: 100.,
+        ..default()
+    });
+    run_ticks(&mut app, 2);
+    let fizzing = tablet(&mut app, 1);
+
+    app.world_mut().get_mut::<PlayerIntent>(fizzing).unwrap().up = 1.;
+    app.world_mut().get_mut::<Health>(fizzing).unwrap().0 = 0.01;
+
+    let mut eliminations = Vec::new();
+    for _ in 0..4 {
+        app.update();
+        eliminations.extend(
+            app.world_mut()
+                .resource_mut::<Events<PlayerEliminated>>()
+                .drain(),
+        );
+    }
+
+    assert_eq!(
+        eliminations,
+        [PlayerEliminated {
+            player: 1,
+            cause: EliminationCause::SelfFizz,
+            attacker: None,
+        }]
+    );
+}
+
+#[test]
+fn last_tablet_standing_wins_the_round() {
+    let mut app = headless_app(2);
+    app.insert_resource(MatchScore::new(2, 3));
+    run_ticks(&mut app, 2);
+    assert!(app.world().get_resource::<RoundOutcome>().is_none());
+
+    let loser = tablet(&mut app, 1);
+    app.world_mut().get_mut::<Health>(loser).unwrap().0 = 0.;
+    run_ticks(&mut app, 2);
+
+    assert_eq!(
+        app.world().get_resource::<RoundOutcome>(),
+        Some(&RoundOutcome { winner: Some(0) })
+    );
+    assert_eq!(app.world().resource::<MatchScore>().wins, vec![1, 0]);
+}
+
+#[test]
+fn last_team_standing_wins_the_round() {
+    let mut app = headless_app(3);
+    app.insert_resource(Teams {
+        teams: vec![0, 0, 1],
+        friendly_fire: false,
+    });
+    app.insert_resource(MatchScore::new(2, 3));
+    run_ticks(&mut app, 2);
+
+    let teammate = tablet(&mut app, 1);
+    app.world_mut().get_mut::<Health>(teammate).unwrap().0 = 0.;
+    run_ticks(&mut app, 2);
+    assert!(app.world().get_resource::<RoundOutcome>().is_none());
+
+    let opponent = tablet(&mut app, 2);
+    app.world_mut().get_mut::<Health>(opponent).unwrap().0 = 0.;
+    run_ticks(&mut app, 2);
+
+    assert_eq!(
+        app.world().get_resource::<RoundOutcome>(),
+        Some(&RoundOutcome { winner: Some(0) })
+    );
+    assert_eq!(app.world().resource::<MatchScore>().wins, vec![1, 0]);
+}
+
+#[test]
+fn same_seed_and_inputs_play_the_same_round() {
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        let mut app = headless_app(2);
+        app.update();
+        app.insert_resource(GameRng::from_seed(42));
+        let first = tablet(&mut app, 0);
+        let second = tablet(&mut app, 1);
+
+        for tick in 0..2 * SECOND {
+            let mut intent = app.world_mut().get_mut::<PlayerIntent>(first).unwrap();
+            intent.up = if tick % 20 < 10 { 1. } else { 0. };
+            intent.right = 0.5;
+            let mut intent = app.world_mut().get_mut::<PlayerIntent>(second).unwrap();
+            intent.left = 1.;
+            intent.down = if tick % 30 < 5 { 1. } else { 0. };
+            app.update();
+        }
+
+        let world = app.world();
+        runs.push([first, second].map(|entity| {
+            (
+                world.get::<Transform>(entity).unwrap().translation,
+                world.get::<Health>(entity).unwrap().0,
+            )
+        }));
+    }
+
+    assert_eq!(runs[0], runs[1]);
+}
+
+// scripted intents of each player of a LAN round
+fn lan_intent(player: usize, tick: usize) -> PlayerIntent {
+    if player == 0 {
+        PlayerIntent {
+            up: if tick % 20 < 10 { 1. } else { 0. },
+            right: 0.5,
+            ..default()
+        }
+    } else {
+        PlayerIntent {
+            left: 1.,
+            down: if tick % 30 < 5 { 1. } else { 0. },
+            ..default()
+        }
+    }
+}
+
+fn tablet_states(world: &World) -> Vec<(usize, Vec3, f32)> {
+    let mut states: Vec<_> = world
+        .iter_entities()
+        .filter_map(|entity| {
+            Some((
+                entity.get::<Player>()?.0,
+                entity.get::<Transform>()?.translation,
+                entity.get::<Health>()?.0,
+            ))
+        })
+        .collect();
+    states.sort_by_key(|(player, _, _)| *player);
+    states
+}
+
+// a loopback port nothing listens on
+fn free_address() -> SocketAddr {
+    UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[test]
+fn lan_peers_play_the_same_round_as_offline() {
+    let addresses: Vec<SocketAddr> = (0..2).map(|_| free_address()).collect();
+    let mut peers: Vec<App> = (0..2)
+        .map(|player| {
+            let mut app = headless_app(2);
+            app.add_plugins(NetcodePlugin);
+            app.insert_resource(RoundSeed(42));
+            app.insert_resource(
+                NetSession::bind(NetConfig {
+                    player,
+                    addresses: addresses.clone(),
+                })
+                .unwrap(),
+            );
+            app.update();
+            app
+        })
+        .collect();
+
+    for _ in 0..2 * SECOND {
+        for (player, app) in peers.iter_mut().enumerate() {
+            let tick = app.world().resource::<NetSession>().tick();
+            let entity = tablet(app, player);
+            *app.world_mut().get_mut::<PlayerIntent>(entity).unwrap() = lan_intent(player, tick);
+            app.update();
+        }
+    }
+
+    let mut offline = headless_app(2);
+    offline.insert_resource(RoundSeed(42));
+    offline.update();
+    let players = [tablet(&mut offline, 0), tablet(&mut offline, 1)];
+    let mut offline_states = vec![tablet_states(offline.world())];
+    for tick in 0..2 * SECOND {
+        for (player, entity) in players.iter().enumerate() {
+            *offline
+                .world_mut()
+                .get_mut::<PlayerIntent>(*entity)
+                .unwrap() = lan_intent(player, tick);
+        }
+        offline.update();
+        offline_states.push(tablet_states(offline.world()));
+    }
+
+    for app in &peers {
+        let session = app.world().resource::<NetSession>();
+        assert!(session.confirmed_tick() > SECOND);
+        let confirmed = confirmed_world(app.world()).unwrap();
+        assert_eq!(
+            tablet_states(confirmed),
+            offline_states[session.confirmed_tick()]
+        );
+    }
+    assert!(peers
+        .iter()
+        .any(|app| app.world().resource::<NetSession>().rollbacks() > 0));
+}
+
+#[test]
+fn thin_clients_follow_the_server() {
+    let address = free_address();
+    let mut server = server_app(ServerConfig {
+        address,
+        player_number: 2,
+        best_of: 3,
+    });
+    server.insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ));
+    // items fall in early enough to show up in the snapshots
+    server.insert_resource(GameTuning {
+        pickup_interval: 8,
+        ..default()
+    });
+    let mut clients = [
+        ThinClient::connect(address).unwrap(),
+        ThinClient::connect(address).unwrap(),
+    ];
+
+    for _ in 0..SECOND {
+        for client in &clients {
+            // the first player to join thrusts up, the other one stays idle
+            let intent = match client.player() {
+                Some(0) => PlayerIntent {
+                    up: 1.,
+                    ..default()
+                },
+                _ => PlayerIntent::default(),
+            };
+            client.send(intent);
+        }
+        server.update();
+        for client in &mut clients {
+            client.receive();
+        }
+    }
+
+    let mut players: Vec<usize> = clients.iter().filter_map(ThinClient::player).collect();
+    players.sort();
+    assert_eq!(players, [0, 1]);
+
+    let [first, second] = &clients;
+    let (first, second) = (first.latest().unwrap(), second.latest().unwrap());
+    assert_eq!(first, second);
+    assert_eq!(first.tablets.len(), 2);
+    assert!(first.tablets[0].translation.y > first.tablets[1].translation.y);
+    assert!(!first.pickups.is_empty());
+    assert_eq!(clients[0].sample_pickups(first.tick as f32), first.pickups);
+
+    let latest = first.tick as f32;
+    let from = clients[0].sample(latest - 1.)[0].translation;
+    let to = clients[0].sample(latest)[0].translation;
+    let halfway = clients[0].sample(latest - 0.5)[0].translation;
+    assert!(halfway.distance(from.lerp(to, 0.5)) < 1e-3);
+}
+
+#[test]
+fn spectator_watches_without_taking_a_tablet() {
+    let address = free_address();
+    let mut server = server_app(ServerConfig {
+        address,
+        player_number: 1,
+        best_of: 1,
+    });
+    server.insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ));
+    let mut clients = [
+        ThinClient::spectate(address).unwrap(),
+        ThinClient::connect(address).unwrap(),
+    ];
+
+    for _ in 0..8 {
+        for client in &clients {
+            client.send(PlayerIntent::default());
+        }
+        server.update();
+        for client in &mut clients {
+            client.receive();
+        }
+    }
+
+    let [spectator, player] = &clients;
+    assert_eq!(spectator.player(), None);
+    assert_eq!(player.player(), Some(0));
+    assert!(spectator.latest().is_some());
+    assert_eq!(spectator.latest(), player.latest());
+}